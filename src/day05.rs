@@ -1,57 +1,180 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::str::FromStr;
 
 #[derive(Clone)]
-struct MapLine {
-    destination_range_start: u32,
-    source_range_start: u32,
-    range_length: u32,
+pub struct MapLine {
+    destination_range_start: u64,
+    source_range_start: u64,
+    range_length: u64,
+}
+
+impl MapLine {
+    fn last_offset(&self) -> Option<u64> {
+        (self.range_length > 0).then(|| {
+            (self.range_length - 1)
+                .min(u64::MAX - self.source_range_start)
+                .min(u64::MAX - self.destination_range_start)
+        })
+    }
+
+    fn source_range(&self) -> Option<(u64, u64)> {
+        self.last_offset()
+            .map(|offset| (self.source_range_start, self.source_range_start + offset))
+    }
+
+    fn destination_range(&self) -> Option<(u64, u64)> {
+        self.last_offset().map(|offset| {
+            (
+                self.destination_range_start,
+                self.destination_range_start + offset,
+            )
+        })
+    }
+}
+
+fn intersection(range1: (u64, u64), range2: (u64, u64)) -> Option<(u64, u64)> {
+    let start = u64::max(range1.0, range2.0);
+    let end = u64::min(range1.1, range2.1);
+
+    (start <= end).then_some((start, end))
 }
 
 #[derive(Clone)]
-struct CategoryMap(Vec<MapLine>);
+pub struct CategoryMap {
+    source: String,
+    destination: String,
+    lines: Vec<MapLine>,
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum MapIssue {
+    OverlappingSources {
+        lines: (usize, usize),
+        range: (u64, u64),
+    },
+    CollidingDestinations {
+        lines: (usize, usize),
+        range: (u64, u64),
+    },
+    Gap {
+        range: (u64, u64),
+    },
+}
+
+impl CategoryMap {
+    pub fn validate(&self) -> Vec<MapIssue> {
+        let mut issues = Vec::new();
+
+        for (index1, line1) in self.lines.iter().enumerate() {
+            for (index2, line2) in self.lines.iter().enumerate().skip(index1 + 1) {
+                if let Some(range) = line1
+                    .source_range()
+                    .zip(line2.source_range())
+                    .and_then(|(range1, range2)| intersection(range1, range2))
+                {
+                    issues.push(MapIssue::OverlappingSources {
+                        lines: (index1, index2),
+                        range,
+                    });
+                }
+
+                if let Some(range) = line1
+                    .destination_range()
+                    .zip(line2.destination_range())
+                    .and_then(|(range1, range2)| intersection(range1, range2))
+                {
+                    issues.push(MapIssue::CollidingDestinations {
+                        lines: (index1, index2),
+                        range,
+                    });
+                }
+            }
+        }
+
+        let mut source_ranges = self
+            .lines
+            .iter()
+            .filter_map(MapLine::source_range)
+            .collect::<Vec<_>>();
+        source_ranges.sort_unstable();
+
+        let Some(&(mut covered_end, _)) = source_ranges.first() else {
+            return issues;
+        };
+
+        for (start, end) in source_ranges {
+            if covered_end < start {
+                issues.push(MapIssue::Gap {
+                    range: (covered_end, start - 1),
+                });
+            }
+
+            if end == u64::MAX {
+                break;
+            }
+
+            covered_end = covered_end.max(end + 1);
+        }
+
+        issues
+    }
+}
 
-struct Almanac {
-    seeds: Vec<u32>,
+pub struct Almanac {
+    seeds: Vec<u64>,
     maps: Vec<CategoryMap>,
 }
 
+impl FromStr for Almanac {
+    type Err = aoc_parse::ParseError;
+
+    fn from_str(almanac: &str) -> Result<Self, Self::Err> {
+        use aoc_parse::{parser, prelude::*};
+
+        let almanac_parser = parser!(
+            rule map_line: MapLine = destination_range_start:u64 " " source_range_start:u64 " " range_length:u64 =>
+                MapLine {
+                    destination_range_start,
+                    source_range_start,
+                    range_length,
+                };
+
+            rule category_map: CategoryMap =
+                categories:line(string(alpha+) "-to-" string(alpha+) " map:")
+                lines:lines(map_line) =>
+                    CategoryMap {
+                        source: categories.0,
+                        destination: categories.1,
+                        lines,
+                    };
+
+            seeds:section(line("seeds: " repeat_sep(u64, " ")))
+            maps:sections(category_map) =>
+                Almanac {
+                    seeds,
+                    maps,
+                }
+        );
+
+        almanac_parser.parse(almanac)
+    }
+}
+
 #[aoc_generator(day5)]
 fn parse_input(almanac: &str) -> Almanac {
-    use aoc_parse::{parser, prelude::*};
-
-    let almanac_parser = parser!(
-        rule map_line: MapLine = destination_range_start:u32 " " source_range_start:u32 " " range_length:u32 =>
-            MapLine {
-                destination_range_start,
-                source_range_start,
-                range_length,
-            };
-
-        rule category_map: CategoryMap =
-            line(string(any_char+))
-            map_lines:lines(map_line) =>
-                CategoryMap(map_lines);
-
-        seeds:section(line("seeds: " repeat_sep(u32, " ")))
-        maps:sections(category_map) =>
-            Almanac {
-                seeds,
-                maps,
-            }
-    );
-
-    almanac_parser.parse(almanac).unwrap()
+    almanac.parse().unwrap()
 }
 
-fn location(seed: &u32, maps: &[CategoryMap]) -> u32 {
+pub fn location<'a>(seed: &u64, maps: impl IntoIterator<Item = &'a CategoryMap>) -> u64 {
     let mut id = *seed;
 
-    for category_map in maps.iter() {
-        for map_line in category_map.0.iter() {
-            if id >= map_line.source_range_start
-                && id < map_line.source_range_start + map_line.range_length
+    for category_map in maps {
+        for map_line in category_map.lines.iter() {
+            if let Some((start, _)) = map_line
+                .source_range()
+                .filter(|&(start, end)| start <= id && id <= end)
             {
-                id = map_line.destination_range_start + (id - map_line.source_range_start);
+                id = map_line.destination_range_start + (id - start);
                 break;
             }
         }
@@ -60,63 +183,260 @@ fn location(seed: &u32, maps: &[CategoryMap]) -> u32 {
     id
 }
 
-#[aoc(day5, part1)]
-fn part1(almanac: &Almanac) -> u32 {
-    almanac
-        .seeds
-        .iter()
-        .map(|seed| location(seed, &almanac.maps))
-        .min()
-        .unwrap()
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+struct Segment {
+    start: u64,
+    end: u64,
+    destination_start: u64,
 }
 
-#[aoc(day5, part2)]
-fn part2(almanac: &Almanac) -> u32 {
-    let mut source_ranges = Vec::new();
+impl Segment {
+    fn apply(&self, id: u64) -> u64 {
+        self.destination_start + (id - self.start)
+    }
 
-    for seed_range in almanac.seeds.chunks_exact(2) {
-        source_ranges.push((seed_range[0], seed_range[0] + seed_range[1] - 1));
+    fn destination_end(&self) -> u64 {
+        self.apply(self.end)
     }
+}
 
-    for category_map in almanac.maps.iter() {
-        let mut destination_ranges = Vec::new();
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct IntervalMap(Vec<Segment>);
 
-        'source_ranges: while let Some(source_range) = source_ranges.pop() {
-            for map_line in category_map.0.iter() {
-                let line_source_start = map_line.source_range_start;
-                let line_source_end = map_line.source_range_start + map_line.range_length - 1;
+impl IntervalMap {
+    pub fn identity() -> Self {
+        IntervalMap(vec![Segment {
+            start: 0,
+            end: u64::MAX,
+            destination_start: 0,
+        }])
+    }
 
-                let line_destination_start = map_line.destination_range_start;
+    fn from_segments(segments: impl IntoIterator<Item = Segment>) -> Self {
+        let mut merged_segments: Vec<Segment> = Vec::new();
+
+        for segment in segments {
+            match merged_segments.last_mut() {
+                Some(last)
+                    if last.end + 1 == segment.start
+                        && last.destination_end().checked_add(1)
+                            == Some(segment.destination_start) =>
+                {
+                    last.end = segment.end;
+                }
+                _ => merged_segments.push(segment),
+            }
+        }
 
-                if line_source_start <= source_range.1 && line_source_end >= source_range.0 {
-                    if source_range.0 < line_source_start {
-                        source_ranges.push((source_range.0, line_source_start - 1));
-                    }
+        IntervalMap(merged_segments)
+    }
 
-                    if source_range.1 > line_source_end {
-                        source_ranges.push((line_source_end + 1, source_range.1));
-                    }
+    pub fn apply(&self, id: u64) -> u64 {
+        let index = self.0.partition_point(|segment| segment.end < id);
 
-                    destination_ranges.push((
-                        u32::max(line_source_start, source_range.0) - line_source_start
-                            + line_destination_start,
-                        u32::min(line_source_end, source_range.1) - line_source_start
-                            + line_destination_start,
-                    ));
+        self.0[index].apply(id)
+    }
 
-                    continue 'source_ranges;
-                }
+    pub fn apply_range(&self, range: (u64, u64)) -> Vec<(u64, u64)> {
+        let first = self.0.partition_point(|segment| segment.end < range.0);
+
+        self.0[first..]
+            .iter()
+            .take_while(|segment| segment.start <= range.1)
+            .map(|segment| {
+                (
+                    segment.apply(u64::max(segment.start, range.0)),
+                    segment.apply(u64::min(segment.end, range.1)),
+                )
+            })
+            .collect()
+    }
+
+    pub fn preimage(&self, range: (u64, u64)) -> Vec<(u64, u64)> {
+        let mut source_ranges = self
+            .0
+            .iter()
+            .filter_map(|segment| {
+                intersection(
+                    (segment.destination_start, segment.destination_end()),
+                    range,
+                )
+                .map(|(start, end)| {
+                    (
+                        segment.start + (start - segment.destination_start),
+                        segment.start + (end - segment.destination_start),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        source_ranges.sort_unstable();
+
+        let mut merged_ranges: Vec<(u64, u64)> = Vec::new();
+
+        for (start, end) in source_ranges {
+            match merged_ranges.last_mut() {
+                Some(last) if last.1.checked_add(1) == Some(start) => last.1 = end,
+                _ => merged_ranges.push((start, end)),
             }
+        }
+
+        merged_ranges
+    }
+
+    pub fn compose(&self, other: &IntervalMap) -> IntervalMap {
+        let mut segments = Vec::new();
+
+        for segment in self.0.iter() {
+            let mut start = segment.start;
 
-            destination_ranges.push((source_range.0, source_range.1));
+            for (destination_start, destination_end) in
+                other.apply_range((segment.destination_start, segment.destination_end()))
+            {
+                let end = start + (destination_end - destination_start);
+
+                segments.push(Segment {
+                    start,
+                    end,
+                    destination_start,
+                });
+
+                start = end.wrapping_add(1);
+            }
         }
 
-        source_ranges = destination_ranges;
+        IntervalMap::from_segments(segments)
     }
+}
+
+impl From<&CategoryMap> for IntervalMap {
+    fn from(category_map: &CategoryMap) -> Self {
+        let line_ranges = category_map
+            .lines
+            .iter()
+            .filter_map(|map_line| {
+                map_line
+                    .source_range()
+                    .map(|(start, end)| (start, end, map_line.destination_range_start))
+            })
+            .collect::<Vec<_>>();
+
+        let mut breakpoints = line_ranges
+            .iter()
+            .flat_map(|&(start, end, _)| [Some(start), end.checked_add(1)])
+            .flatten()
+            .chain([0])
+            .collect::<Vec<_>>();
+        breakpoints.sort_unstable();
+        breakpoints.dedup();
+
+        let segments = breakpoints.iter().enumerate().map(|(index, &start)| {
+            let end = breakpoints
+                .get(index + 1)
+                .map_or(u64::MAX, |next_start| next_start - 1);
+
+            let destination_start = line_ranges
+                .iter()
+                .find(|&&(line_start, line_end, _)| line_start <= start && start <= line_end)
+                .map_or(start, |&(line_start, _, line_destination_start)| {
+                    line_destination_start + (start - line_start)
+                });
+
+            Segment {
+                start,
+                end,
+                destination_start,
+            }
+        });
+
+        IntervalMap::from_segments(segments)
+    }
+}
 
-    source_ranges
+impl Almanac {
+    pub fn chain(&self, source: &str, destination: &str) -> Option<Vec<&CategoryMap>> {
+        let mut chain = Vec::new();
+        let mut category = source;
+
+        while category != destination {
+            if chain.len() == self.maps.len() {
+                return None;
+            }
+
+            let category_map = self
+                .maps
+                .iter()
+                .find(|category_map| category_map.source == category)?;
+
+            chain.push(category_map);
+            category = &category_map.destination;
+        }
+
+        Some(chain)
+    }
+
+    pub fn translation(&self, source: &str, destination: &str) -> Option<IntervalMap> {
+        Some(
+            self.chain(source, destination)?
+                .into_iter()
+                .fold(IntervalMap::identity(), |interval_map, category_map| {
+                    interval_map.compose(&IntervalMap::from(category_map))
+                }),
+        )
+    }
+
+    pub fn seed_to_location(&self) -> IntervalMap {
+        self.translation("seed", "location").unwrap()
+    }
+
+    pub fn seeds_for_locations(&self, locations: (u64, u64)) -> Vec<(u64, u64)> {
+        self.seed_to_location().preimage(locations)
+    }
+
+    pub fn seeds_for_location(&self, location: u64) -> Vec<(u64, u64)> {
+        self.seeds_for_locations((location, location))
+    }
+
+    pub fn validate(&self) -> Vec<(usize, MapIssue)> {
+        self.maps
+            .iter()
+            .enumerate()
+            .flat_map(|(map_index, category_map)| {
+                category_map
+                    .validate()
+                    .into_iter()
+                    .map(move |issue| (map_index, issue))
+            })
+            .collect()
+    }
+}
+
+#[aoc(day5, part1)]
+fn part1(almanac: &Almanac) -> u64 {
+    let seed_to_location = almanac.seed_to_location();
+
+    almanac
+        .seeds
         .iter()
-        .map(|(range_min, _)| *range_min)
+        .map(|seed| seed_to_location.apply(*seed))
+        .min()
+        .unwrap()
+}
+
+#[aoc(day5, part2)]
+fn part2(almanac: &Almanac) -> u64 {
+    let seed_to_location = almanac.seed_to_location();
+
+    almanac
+        .seeds
+        .chunks_exact(2)
+        .filter(|seed_range| seed_range[1] > 0)
+        .flat_map(|seed_range| {
+            seed_to_location.apply_range((
+                seed_range[0],
+                seed_range[0].saturating_add(seed_range[1] - 1),
+            ))
+        })
+        .map(|(range_min, _)| range_min)
         .min()
         .unwrap()
 }
@@ -168,4 +488,209 @@ humidity-to-location map:
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 46);
     }
+
+    #[test]
+    fn seed_to_location_matches_location() {
+        use rand::Rng;
+
+        let almanac = parse_input(TEST_INPUT);
+        let seed_to_location = almanac.seed_to_location();
+
+        let mut rng = rand::thread_rng();
+
+        for seed in (0..200).chain((0..1000).map(|_| rng.gen::<u64>())) {
+            assert_eq!(
+                seed_to_location.apply(seed),
+                location(&seed, almanac.chain("seed", "location").unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn interval_map_without_overflow() {
+        let category_map = CategoryMap {
+            source: String::from("seed"),
+            destination: String::from("seed"),
+            lines: vec![MapLine {
+                destination_range_start: 0,
+                source_range_start: u64::MAX - 9,
+                range_length: 20,
+            }],
+        };
+        let interval_map = IntervalMap::from(&category_map);
+
+        assert_eq!(interval_map.apply(u64::MAX), 9);
+        assert_eq!(interval_map.apply(u64::MAX - 10), u64::MAX - 10);
+        assert_eq!(
+            interval_map.apply_range((u64::MAX - 11, u64::MAX)),
+            vec![(u64::MAX - 11, u64::MAX - 10), (0, 9)]
+        );
+        assert_eq!(
+            interval_map.compose(&interval_map).apply(u64::MAX),
+            location(&u64::MAX, &[category_map.clone(), category_map])
+        );
+    }
+
+    #[test]
+    fn seeds_for_location_example() {
+        let almanac = parse_input(TEST_INPUT);
+
+        assert!(almanac
+            .seeds_for_location(46)
+            .iter()
+            .any(|&(start, end)| start <= 82 && 82 <= end));
+
+        let seed_to_location = almanac.seed_to_location();
+
+        for location in 0..200 {
+            for (start, end) in almanac.seeds_for_location(location) {
+                assert_eq!(seed_to_location.apply(start), location);
+                assert_eq!(seed_to_location.apply(end), location);
+            }
+        }
+
+        assert_eq!(
+            almanac
+                .seeds_for_locations((0, 99))
+                .iter()
+                .map(|(start, end)| end - start + 1)
+                .sum::<u64>(),
+            100
+        );
+    }
+
+    #[test]
+    fn validate_example() {
+        assert!(parse_input(TEST_INPUT).validate().is_empty());
+
+        let category_map = CategoryMap {
+            source: String::from("seed"),
+            destination: String::from("soil"),
+            lines: vec![
+                MapLine {
+                    destination_range_start: 50,
+                    source_range_start: 0,
+                    range_length: 10,
+                },
+                MapLine {
+                    destination_range_start: 55,
+                    source_range_start: 5,
+                    range_length: 10,
+                },
+                MapLine {
+                    destination_range_start: 100,
+                    source_range_start: 20,
+                    range_length: 5,
+                },
+            ],
+        };
+
+        assert_eq!(
+            category_map.validate(),
+            vec![
+                MapIssue::OverlappingSources {
+                    lines: (0, 1),
+                    range: (5, 9),
+                },
+                MapIssue::CollidingDestinations {
+                    lines: (0, 1),
+                    range: (55, 59),
+                },
+                MapIssue::Gap { range: (15, 19) },
+            ]
+        );
+
+        let category_map = CategoryMap {
+            source: String::from("seed"),
+            destination: String::from("soil"),
+            lines: vec![
+                MapLine {
+                    destination_range_start: 0,
+                    source_range_start: 1,
+                    range_length: u64::MAX,
+                },
+                MapLine {
+                    destination_range_start: 0,
+                    source_range_start: 5,
+                    range_length: 6,
+                },
+            ],
+        };
+
+        assert!(!category_map
+            .validate()
+            .iter()
+            .any(|issue| matches!(issue, MapIssue::Gap { .. })));
+    }
+
+    #[test]
+    fn translation_example() {
+        let almanac = parse_input(TEST_INPUT);
+
+        let soil_to_humidity = almanac.translation("soil", "humidity").unwrap();
+
+        assert_eq!(soil_to_humidity.apply(81), 78);
+        assert_eq!(soil_to_humidity.apply(14), 43);
+        assert_eq!(soil_to_humidity.apply(57), 82);
+        assert_eq!(soil_to_humidity.apply(13), 35);
+
+        assert_eq!(
+            almanac.translation("water", "water"),
+            Some(IntervalMap::identity())
+        );
+        assert!(almanac.translation("humidity", "soil").is_none());
+        assert!(almanac.translation("seed", "sunlight").is_none());
+    }
+
+    #[test]
+    fn maps_in_any_order() {
+        let mut sections = TEST_INPUT.split("\n\n").collect::<Vec<_>>();
+        sections[1..].reverse();
+
+        let almanac = parse_input(&sections.join("\n\n"));
+
+        assert_eq!(part1(&almanac), 35);
+        assert_eq!(part2(&almanac), 46);
+    }
+
+    #[test]
+    fn almanac_from_str() {
+        let almanac = TEST_INPUT.parse::<Almanac>().unwrap();
+
+        assert_eq!(almanac.seed_to_location().apply(79), 82);
+        assert!("seeds: 79\n\nseed-to-soil map:\n50 98"
+            .parse::<Almanac>()
+            .is_err());
+    }
+
+    #[test]
+    fn map_lines_clipped_at_u64_max() {
+        let category_map = CategoryMap {
+            source: String::from("seed"),
+            destination: String::from("location"),
+            lines: vec![MapLine {
+                destination_range_start: u64::MAX - 1,
+                source_range_start: 0,
+                range_length: 5,
+            }],
+        };
+        let interval_map = IntervalMap::from(&category_map);
+
+        for seed in 0..5 {
+            assert_eq!(location(&seed, [&category_map]), interval_map.apply(seed));
+        }
+
+        assert_eq!(location(&1, [&category_map]), u64::MAX);
+        assert_eq!(location(&2, [&category_map]), 2);
+
+        let almanac = format!(
+            "seeds: {} 5 7 0\n\nseed-to-location map:\n0 {} 1",
+            u64::MAX,
+            u64::MAX
+        )
+        .parse::<Almanac>()
+        .unwrap();
+
+        assert_eq!(part2(&almanac), 0);
+    }
 }
@@ -1,4 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use num::{BigUint, Zero};
 
 #[aoc_generator(day6)]
 fn parse_input(times_and_distances: &str) -> Vec<(u64, u64)> {
@@ -15,33 +16,65 @@ fn parse_input(times_and_distances: &str) -> Vec<(u64, u64)> {
     times.into_iter().zip(distances).collect()
 }
 
-fn ways_to_beat_the_record(time: u64, record_distance: u64) -> usize {
-    let discriminant = time * time - 4 * record_distance;
+fn distance(hold_time: &BigUint, time: &BigUint) -> BigUint {
+    hold_time * (time - hold_time)
+}
+
+pub fn winning_hold_times(time: &BigUint, record_distance: &BigUint) -> Option<(BigUint, BigUint)> {
+    let time_squared = time * time;
+    let four_record_distances = record_distance * 4u32;
+
+    if time_squared < four_record_distances {
+        return None;
+    }
+
+    let discriminant = time_squared - four_record_distances;
+    let half_time = time / 2u32;
 
-    let min_root = ((time as f64 - (discriminant as f64).sqrt()) / 2.0).floor() as usize + 1;
-    let max_root = ((time as f64 + (discriminant as f64).sqrt()) / 2.0).ceil() as usize - 1;
+    let mut min_hold_time = (time - discriminant.sqrt()) / 2u32;
+
+    while !min_hold_time.is_zero() && distance(&(&min_hold_time - 1u32), time) > *record_distance {
+        min_hold_time -= 1u32;
+    }
+
+    while min_hold_time <= half_time && distance(&min_hold_time, time) <= *record_distance {
+        min_hold_time += 1u32;
+    }
 
-    max_root - min_root + 1
+    (min_hold_time <= half_time).then(|| {
+        let max_hold_time = time - &min_hold_time;
+        (min_hold_time, max_hold_time)
+    })
+}
+
+pub fn ways_to_beat_the_record(time: &BigUint, record_distance: &BigUint) -> BigUint {
+    winning_hold_times(time, record_distance)
+        .map(|(min_hold_time, max_hold_time)| max_hold_time - min_hold_time + 1u32)
+        .unwrap_or_default()
 }
 
 #[aoc(day6, part1)]
-fn part1(times_and_distances: &[(u64, u64)]) -> usize {
+fn part1(times_and_distances: &[(u64, u64)]) -> BigUint {
     times_and_distances
         .iter()
-        .map(|(time, distance)| ways_to_beat_the_record(*time, *distance))
+        .map(|(time, distance)| {
+            ways_to_beat_the_record(&BigUint::from(*time), &BigUint::from(*distance))
+        })
         .product()
 }
 
+fn concatenate(numbers: impl Iterator<Item = u64>) -> BigUint {
+    numbers.fold(BigUint::zero(), |acc, number| {
+        acc * BigUint::from(10u32).pow(number.checked_ilog10().unwrap_or(0) + 1) + number
+    })
+}
+
 #[aoc(day6, part2)]
-fn part2(times_and_distances: &[(u64, u64)]) -> usize {
-    let (time, distance) = times_and_distances.iter().fold((0, 0), |acc, e| {
-        (
-            acc.0 * 10u64.pow(e.0.checked_ilog10().unwrap() + 1) + e.0,
-            acc.1 * 10u64.pow(e.1.checked_ilog10().unwrap() + 1) + e.1,
-        )
-    });
+fn part2(times_and_distances: &[(u64, u64)]) -> BigUint {
+    let time = concatenate(times_and_distances.iter().map(|(time, _)| *time));
+    let distance = concatenate(times_and_distances.iter().map(|(_, distance)| *distance));
 
-    ways_to_beat_the_record(time, distance)
+    ways_to_beat_the_record(&time, &distance)
 }
 
 #[cfg(test)]
@@ -51,13 +84,91 @@ mod tests {
     static TEST_INPUT: &str = r"Time:      7  15   30
 Distance:  9  40  200";
 
+    fn hold_times(time: u128, record_distance: u128) -> Option<(u128, u128)> {
+        winning_hold_times(&BigUint::from(time), &BigUint::from(record_distance)).map(
+            |(min_hold_time, max_hold_time)| {
+                (
+                    u128::try_from(min_hold_time).unwrap(),
+                    u128::try_from(max_hold_time).unwrap(),
+                )
+            },
+        )
+    }
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input(TEST_INPUT)), 288);
+        assert_eq!(part1(&parse_input(TEST_INPUT)), BigUint::from(288u32));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input(TEST_INPUT)), 71_503);
+        assert_eq!(part2(&parse_input(TEST_INPUT)), BigUint::from(71_503u32));
+    }
+
+    #[test]
+    fn winning_hold_times_example() {
+        assert_eq!(hold_times(7, 9), Some((2, 5)));
+        assert_eq!(hold_times(15, 40), Some((4, 11)));
+        assert_eq!(hold_times(30, 200), Some((11, 19)));
+        assert_eq!(hold_times(71_530, 940_200), Some((14, 71_516)));
+    }
+
+    #[test]
+    fn winning_hold_times_perfect_square_discriminant() {
+        assert_eq!(hold_times(10, 24), Some((5, 5)));
+        assert_eq!(hold_times(10, 21), Some((4, 6)));
+        assert_eq!(hold_times(10, 25), None);
+        assert_eq!(hold_times(10, 26), None);
+        assert_eq!(hold_times(4, 3), Some((2, 2)));
+        assert_eq!(hold_times(4, 4), None);
+        assert_eq!(hold_times(2, 0), Some((1, 1)));
+        assert_eq!(hold_times(0, 0), None);
+    }
+
+    #[test]
+    fn winning_hold_times_large() {
+        let time = u64::MAX as u128;
+        let half = time / 2;
+
+        assert_eq!(
+            hold_times(time, half * (half + 1) - 1),
+            Some((half, half + 1))
+        );
+        assert_eq!(
+            hold_times(time, (half - 1) * (half + 2) - 1),
+            Some((half - 1, half + 2))
+        );
+        assert_eq!(hold_times(time, half * (half + 1)), None);
+
+        assert_eq!(hold_times(1 << 64, 1), Some((1, (1 << 64) - 1)));
+        assert_eq!(
+            hold_times(u128::MAX, u128::MAX - 2),
+            Some((1, u128::MAX - 1))
+        );
+        assert_eq!(hold_times(u128::MAX, u128::MAX), Some((2, u128::MAX - 2)));
+
+        let time = BigUint::from(1u32) << 200;
+        let half = &time / 2u32;
+
+        assert_eq!(
+            winning_hold_times(&time, &(&half * &half - 1u32)),
+            Some((half.clone(), half.clone()))
+        );
+        assert_eq!(winning_hold_times(&time, &(&half * &half)), None);
+    }
+
+    #[test]
+    fn concatenate_large() {
+        assert_eq!(
+            concatenate([u64::MAX, 0, u64::MAX].into_iter()),
+            format!("{}0{}", u64::MAX, u64::MAX).parse().unwrap()
+        );
+        assert_eq!(
+            part2(&[(u64::MAX, 1), (u64::MAX, 1)]),
+            ways_to_beat_the_record(
+                &format!("{}{}", u64::MAX, u64::MAX).parse().unwrap(),
+                &BigUint::from(11u32)
+            )
+        );
     }
 }
@@ -1,10 +1,11 @@
 use crate::day07::Card::*;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-enum Card {
+pub enum Card {
     Ace,
     King,
     Queen,
@@ -18,75 +19,292 @@ enum Card {
     Four,
     Three,
     Two,
-    Joker,
 }
 
-impl Ord for Card {
-    fn cmp(&self, other: &Self) -> Ordering {
-        (*other as usize).cmp(&(*self as usize))
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Ace => 'A',
+            King => 'K',
+            Queen => 'Q',
+            Jack => 'J',
+            Ten => 'T',
+            Nine => '9',
+            Eight => '8',
+            Seven => '7',
+            Six => '6',
+            Five => '5',
+            Four => '4',
+            Three => '3',
+            Two => '2',
+        };
+
+        write!(f, "{label}")
     }
 }
 
-impl PartialOrd for Card {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandCategory {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Hand(pub Vec<Card>);
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|card| write!(f, "{card}"))
     }
 }
 
-const HAND_SIZE: usize = 5;
+pub struct Rules {
+    wild_card: Option<Card>,
+    card_order: Vec<Card>,
+}
 
-type HandType = (usize, usize);
+impl Rules {
+    pub fn new(wild_card: Option<Card>, card_order: Vec<Card>) -> Option<Self> {
+        let cards = Rules::standard().card_order;
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-struct Hand([Card; HAND_SIZE]);
+        (card_order.len() == cards.len() && cards.iter().all(|card| card_order.contains(card)))
+            .then_some(Rules {
+                wild_card,
+                card_order,
+            })
+    }
+
+    pub fn wild_card(&self) -> Option<Card> {
+        self.wild_card
+    }
 
-impl Hand {
-    fn hand_type(&self) -> HandType {
+    pub fn card_order(&self) -> &[Card] {
+        &self.card_order
+    }
+
+    pub fn standard() -> Self {
+        Rules {
+            wild_card: None,
+            card_order: vec![
+                Ace, King, Queen, Jack, Ten, Nine, Eight, Seven, Six, Five, Four, Three, Two,
+            ],
+        }
+    }
+
+    pub fn jokers() -> Self {
+        Rules {
+            wild_card: Some(Jack),
+            card_order: vec![
+                Ace, King, Queen, Ten, Nine, Eight, Seven, Six, Five, Four, Three, Two, Jack,
+            ],
+        }
+    }
+
+    pub fn category(&self, hand: &Hand) -> HandCategory {
         let mut card_counts: HashMap<Card, usize> = HashMap::new();
 
-        for card in self.0.iter() {
+        for card in hand.0.iter() {
             *card_counts.entry(*card).or_insert(0) += 1;
         }
 
-        let joker_count = card_counts.get(&Joker).unwrap_or(&0);
+        let wild_count = self
+            .wild_card
+            .and_then(|wild_card| card_counts.remove(&wild_card))
+            .unwrap_or(0);
 
-        let (most_frequent_card, largest_count) = card_counts
-            .iter()
-            .filter(|(card, _)| **card != Joker)
-            .max_by(|(_, count_a), (_, count_b)| count_a.cmp(count_b))
-            .unwrap_or((&Joker, &0));
+        let mut counts = card_counts.into_values().collect::<Vec<_>>();
+        counts.sort_unstable_by(|count_a, count_b| count_b.cmp(count_a));
+
+        let largest_count = counts.first().unwrap_or(&0) + wild_count;
+        let second_largest_count = *counts.get(1).unwrap_or(&0);
+
+        match (largest_count, second_largest_count) {
+            (5.., _) => HandCategory::FiveOfAKind,
+            (4, _) => HandCategory::FourOfAKind,
+            (3, 2..) => HandCategory::FullHouse,
+            (3, _) => HandCategory::ThreeOfAKind,
+            (2, 2..) => HandCategory::TwoPair,
+            (2, _) => HandCategory::OnePair,
+            _ => HandCategory::HighCard,
+        }
+    }
+
+    fn strength(&self, card: &Card) -> usize {
+        self.card_order.len() - self.card_order.iter().position(|c| c == card).unwrap()
+    }
+
+    pub fn compare(&self, hand_a: &Hand, hand_b: &Hand) -> Ordering {
+        self.category(hand_a)
+            .cmp(&self.category(hand_b))
+            .then_with(|| {
+                hand_a
+                    .0
+                    .iter()
+                    .map(|card| self.strength(card))
+                    .cmp(hand_b.0.iter().map(|card| self.strength(card)))
+            })
+    }
+
+    fn order(&self, camel_cards: &[(Hand, Bid)]) -> Vec<usize> {
+        let mut order = (0..camel_cards.len()).collect::<Vec<_>>();
 
-        let second_largest_count = card_counts
+        order.sort_by(|&index_a, &index_b| {
+            self.compare(&camel_cards[index_a].0, &camel_cards[index_b].0)
+        });
+
+        order
+    }
+
+    pub fn ranking(&self, camel_cards: &[(Hand, Bid)]) -> Vec<RankedHand> {
+        self.order(camel_cards)
+            .into_iter()
+            .enumerate()
+            .map(|(position, index)| {
+                let (hand, bid) = camel_cards[index].clone();
+
+                RankedHand {
+                    category: self.category(&hand),
+                    hand,
+                    bid,
+                    rank: position as u32 + 1,
+                }
+            })
+            .collect()
+    }
+
+    pub fn ranks(&self, camel_cards: &[(Hand, Bid)]) -> Vec<u32> {
+        let mut ranks = vec![0; camel_cards.len()];
+
+        for (position, index) in self.order(camel_cards).into_iter().enumerate() {
+            ranks[index] = position as u32 + 1;
+        }
+
+        ranks
+    }
+
+    pub fn best_substitution(&self, hand: &Hand) -> Option<Card> {
+        let wild_card = self
+            .wild_card
+            .filter(|wild_card| hand.0.contains(wild_card))?;
+
+        let mut card_counts: HashMap<Card, usize> = HashMap::new();
+
+        for card in hand.0.iter().filter(|card| **card != wild_card) {
+            *card_counts.entry(*card).or_insert(0) += 1;
+        }
+
+        self.card_order
             .iter()
-            .filter(|(card, _)| **card != Joker && **card != *most_frequent_card)
-            .max_by(|(_, count_a), (_, count_b)| count_a.cmp(count_b))
-            .map(|(_, count)| count)
-            .unwrap_or(&0);
+            .filter(|card| **card != wild_card)
+            .max_by(|card_a, card_b| {
+                card_counts
+                    .get(card_a)
+                    .cmp(&card_counts.get(card_b))
+                    .then(self.strength(card_a).cmp(&self.strength(card_b)))
+            })
+            .copied()
+    }
 
-        (*largest_count + *joker_count, *second_largest_count)
+    pub fn substitute(&self, hand: &Hand) -> Hand {
+        match (self.wild_card, self.best_substitution(hand)) {
+            (Some(wild_card), Some(substitution)) => Hand(
+                hand.0
+                    .iter()
+                    .map(|card| {
+                        if *card == wild_card {
+                            substitution
+                        } else {
+                            *card
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => hand.clone(),
+        }
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.hand_type()
-            .cmp(&other.hand_type())
-            .then(self.0.cmp(&other.0))
-    }
+pub type Bid = u32;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RankedHand {
+    pub hand: Hand,
+    pub bid: Bid,
+    pub category: HandCategory,
+    pub rank: u32,
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for RankedHand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:?} {}",
+            self.rank, self.hand, self.category, self.bid
+        )
     }
 }
 
-type Bid = u32;
+pub fn ranking_report(ranking: &[RankedHand]) -> String {
+    ranking
+        .iter()
+        .map(|ranked_hand| ranked_hand.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn substitution_report(camel_cards: &[(Hand, Bid)], rules: &Rules) -> String {
+    camel_cards
+        .iter()
+        .map(|(hand, _)| match rules.best_substitution(hand) {
+            Some(substitution) => format!(
+                "{hand} {}->{substitution} {:?}",
+                rules.wild_card.unwrap(),
+                rules.category(hand)
+            ),
+            None => format!("{hand} {:?}", rules.category(hand)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Statistics {
+    pub categories: BTreeMap<HandCategory, usize>,
+    pub wild_categories: BTreeMap<HandCategory, usize>,
+    pub changed_ranks: usize,
+}
+
+pub fn statistics(camel_cards: &[(Hand, Bid)], rules: &Rules, wild_rules: &Rules) -> Statistics {
+    let category_counts = |rules: &Rules| {
+        let mut counts = BTreeMap::new();
+
+        for (hand, _) in camel_cards.iter() {
+            *counts.entry(rules.category(hand)).or_insert(0) += 1;
+        }
+
+        counts
+    };
+
+    Statistics {
+        categories: category_counts(rules),
+        wild_categories: category_counts(wild_rules),
+        changed_ranks: rules
+            .ranks(camel_cards)
+            .into_iter()
+            .zip(wild_rules.ranks(camel_cards))
+            .filter(|(rank, wild_rank)| rank != wild_rank)
+            .count(),
+    }
+}
 
 #[aoc_generator(day7)]
 fn parse_input(camel_cards: &str) -> Vec<(Hand, Bid)> {
     use aoc_parse::{parser, prelude::*};
-    use Card::*;
 
     let parser = parser!(
         rule card: Card = {
@@ -105,7 +323,7 @@ fn parse_input(camel_cards: &str) -> Vec<(Hand, Bid)> {
             "2" => Two,
         };
 
-        rule hand: Hand = cards:card+ => Hand(cards.try_into().unwrap());
+        rule hand: Hand = cards:card+ => Hand(cards);
 
         lines(hand " " bid:u32)
     );
@@ -113,45 +331,22 @@ fn parse_input(camel_cards: &str) -> Vec<(Hand, Bid)> {
     parser.parse(camel_cards).unwrap()
 }
 
-#[aoc(day7, part1)]
-fn part1(camel_cards: &[(Hand, Bid)]) -> u32 {
-    let mut camel_cards = camel_cards.to_vec();
-
-    camel_cards.sort_by(|(hand_a, _), (hand_b, _)| hand_a.cmp(hand_b));
-
-    camel_cards
+fn total_winnings(camel_cards: &[(Hand, Bid)], rules: &Rules) -> u32 {
+    rules
+        .ranking(camel_cards)
         .iter()
-        .enumerate()
-        .map(|(rank, (_, bid))| *bid * (rank as u32 + 1))
+        .map(|ranked_hand| ranked_hand.bid * ranked_hand.rank)
         .sum()
 }
 
+#[aoc(day7, part1)]
+fn part1(camel_cards: &[(Hand, Bid)]) -> u32 {
+    total_winnings(camel_cards, &Rules::standard())
+}
+
 #[aoc(day7, part2)]
 fn part2(camel_cards: &[(Hand, Bid)]) -> u32 {
-    let mut camel_cards = camel_cards
-        .iter()
-        .map(|(hand, bid)| {
-            (
-                Hand(
-                    hand.0
-                        .iter()
-                        .map(|card| if *card == Jack { Joker } else { *card })
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                ),
-                bid,
-            )
-        })
-        .collect::<Vec<_>>();
-
-    camel_cards.sort_by(|(hand_a, _), (hand_b, _)| hand_a.cmp(hand_b));
-
-    camel_cards
-        .iter()
-        .enumerate()
-        .map(|(rank, (_, bid))| *bid * (rank as u32 + 1))
-        .sum()
+    total_winnings(camel_cards, &Rules::jokers())
 }
 
 #[cfg(test)]
@@ -173,4 +368,130 @@ QQQJA 483";
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 5_905);
     }
+
+    #[test]
+    fn ranking_report_example() {
+        let camel_cards = parse_input(TEST_INPUT);
+
+        assert_eq!(
+            ranking_report(&Rules::standard().ranking(&camel_cards)),
+            r"1 32T3K OnePair 765
+2 KTJJT TwoPair 220
+3 KK677 TwoPair 28
+4 T55J5 ThreeOfAKind 684
+5 QQQJA ThreeOfAKind 483"
+        );
+        assert_eq!(
+            ranking_report(&Rules::jokers().ranking(&camel_cards)),
+            r"1 32T3K OnePair 765
+2 KK677 TwoPair 28
+3 T55J5 FourOfAKind 684
+4 QQQJA FourOfAKind 483
+5 KTJJT FourOfAKind 220"
+        );
+    }
+
+    #[test]
+    fn other_hand_sizes() {
+        let camel_cards = parse_input("JJJ 1\nAAK 2\nKAA 3\n2345678 4\n22A3A3A 5");
+
+        assert_eq!(
+            Rules::standard()
+                .ranking(&camel_cards)
+                .iter()
+                .map(|ranked_hand| (ranked_hand.bid, ranked_hand.category))
+                .collect::<Vec<_>>(),
+            vec![
+                (4, HandCategory::HighCard),
+                (3, HandCategory::OnePair),
+                (2, HandCategory::OnePair),
+                (1, HandCategory::ThreeOfAKind),
+                (5, HandCategory::FullHouse),
+            ]
+        );
+
+        let rules = Rules::new(Some(Two), Rules::standard().card_order).unwrap();
+
+        assert_eq!(
+            rules.category(&parse_input("22A3A3A 0")[0].0),
+            HandCategory::FiveOfAKind
+        );
+    }
+
+    #[test]
+    fn substitution_report_example() {
+        let camel_cards = parse_input(TEST_INPUT);
+        let rules = Rules::jokers();
+
+        assert_eq!(
+            substitution_report(&camel_cards, &rules),
+            r"32T3K OnePair
+T55J5 J->5 FourOfAKind
+KK677 TwoPair
+KTJJT J->T FourOfAKind
+QQQJA J->Q FourOfAKind"
+        );
+
+        for hand in ["JJJJJ", "J2345", "2J2J3", "AKJKA"] {
+            let hand = &parse_input(&format!("{hand} 0"))[0].0;
+
+            assert_eq!(
+                Rules::standard().category(&rules.substitute(hand)),
+                rules.category(hand)
+            );
+        }
+
+        assert_eq!(
+            rules.best_substitution(&parse_input("JJJJJ 0")[0].0),
+            Some(Ace)
+        );
+        assert_eq!(
+            Rules::standard().best_substitution(&parse_input("KTJJT 0")[0].0),
+            None
+        );
+    }
+
+    #[test]
+    fn statistics_example() {
+        assert_eq!(
+            statistics(
+                &parse_input(TEST_INPUT),
+                &Rules::standard(),
+                &Rules::jokers()
+            ),
+            Statistics {
+                categories: BTreeMap::from([
+                    (HandCategory::OnePair, 1),
+                    (HandCategory::TwoPair, 2),
+                    (HandCategory::ThreeOfAKind, 2),
+                ]),
+                wild_categories: BTreeMap::from([
+                    (HandCategory::OnePair, 1),
+                    (HandCategory::TwoPair, 1),
+                    (HandCategory::FourOfAKind, 3),
+                ]),
+                changed_ranks: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn rules_new_validates_card_order() {
+        let mut card_order = Rules::standard().card_order;
+        card_order.reverse();
+
+        let rules = Rules::new(None, card_order.clone()).unwrap();
+        let hands = [Hand(vec![Two; 5]), Hand(vec![Ace; 5])];
+
+        assert_eq!(rules.compare(&hands[0], &hands[1]), Ordering::Greater);
+        assert_eq!(rules.card_order(), card_order.as_slice());
+
+        card_order.pop();
+
+        assert!(Rules::new(None, card_order.clone()).is_none());
+
+        card_order.push(Three);
+
+        assert!(Rules::new(Some(Jack), card_order).is_none());
+    }
 }
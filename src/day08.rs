@@ -1,106 +1,335 @@
 use crate::day08::Instruction::{Left, Right};
 use aoc_runner_derive::{aoc, aoc_generator};
-use num::integer::lcm;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
 
-enum Instruction {
+pub enum Instruction {
     Left,
     Right,
 }
 
-struct Network(HashMap<String, (String, String)>);
+pub struct Network(HashMap<String, (String, String)>);
 
-struct Document {
+pub struct Document {
     instructions: Vec<Instruction>,
     network: Network,
 }
 
+impl FromStr for Document {
+    type Err = aoc_parse::ParseError;
+
+    fn from_str(document: &str) -> Result<Self, Self::Err> {
+        use aoc_parse::{parser, prelude::*};
+        use Instruction::*;
+
+        let parser = parser!(
+            instructions:section(line({"L" => Left, "R" => Right}+))
+            network:section(
+                nodes:lines(
+                    key:string(alnum+) " = (" left:string(alnum+) ", " right:string(alnum+) ")" =>
+                        (key, (left, right))
+                ) =>
+                    Network(nodes.into_iter().collect())
+            ) =>
+                Document {
+                    instructions,
+                    network
+                }
+        );
+
+        parser.parse(document)
+    }
+}
+
+impl Document {
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+}
+
 #[aoc_generator(day8)]
 fn parse_input(document: &str) -> Document {
-    use aoc_parse::{parser, prelude::*};
-    use Instruction::*;
-
-    let parser = parser!(
-        instructions:section(line({"L" => Left, "R" => Right}+))
-        network:section(
-            nodes:lines(
-                key:string(alnum+) " = (" left:string(alnum+) ", " right:string(alnum+) ")" =>
-                    (key, (left, right))
-            ) =>
-                Network(nodes.into_iter().collect())
-        ) =>
-            Document {
-                instructions,
-                network
+    document.parse().unwrap()
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum NavigationError {
+    MissingNode(String),
+    NoStart,
+    Unreachable,
+    NoCommonStep,
+}
+
+impl Network {
+    fn next(&self, node: &str, instruction: &Instruction) -> Result<&str, NavigationError> {
+        let next_nodes = self
+            .0
+            .get(node)
+            .ok_or_else(|| NavigationError::MissingNode(node.to_owned()))?;
+
+        Ok(match instruction {
+            Left => &next_nodes.0,
+            Right => &next_nodes.1,
+        })
+    }
+
+    pub fn to_dot(&self, path: &[&str]) -> String {
+        let mut nodes = self.0.iter().collect::<Vec<_>>();
+        nodes.sort_unstable();
+
+        let walked_edges = path
+            .windows(2)
+            .map(|edge| (edge[0], edge[1]))
+            .collect::<HashSet<_>>();
+        let highlight = |walked: bool| if walked { " color=red" } else { "" };
+
+        let mut dot = String::from("digraph network {\n");
+
+        for (node, _) in nodes.iter() {
+            writeln!(
+                dot,
+                "    \"{node}\" [label=\"{node}\"{}];",
+                highlight(path.contains(&node.as_str()))
+            )
+            .unwrap();
+        }
+
+        for (node, (left, right)) in nodes {
+            for (label, next_node) in [("L", left), ("R", right)] {
+                writeln!(
+                    dot,
+                    "    \"{node}\" -> \"{next_node}\" [label=\"{label}\"{}];",
+                    highlight(walked_edges.contains(&(node.as_str(), next_node.as_str())))
+                )
+                .unwrap();
             }
-    );
+        }
+
+        dot.push('}');
+
+        dot
+    }
+}
+
+pub fn walk<'a, F>(
+    document: &'a Document,
+    start: &'a str,
+    is_goal: F,
+) -> Result<Vec<&'a str>, NavigationError>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut visited_states = HashSet::new();
+    let mut path = vec![start];
+
+    for (instruction_index, instruction) in document.instructions.iter().enumerate().cycle() {
+        let current_node = *path.last().unwrap();
+
+        if path.len() > 1 && is_goal(current_node) {
+            return Ok(path);
+        }
+
+        if !visited_states.insert((current_node, instruction_index)) {
+            return Err(NavigationError::Unreachable);
+        }
+
+        path.push(document.network.next(current_node, instruction)?);
+    }
 
-    parser.parse(document).unwrap()
+    Err(NavigationError::Unreachable)
+}
+
+pub fn steps_to_goal<F>(
+    document: &Document,
+    start: &str,
+    is_goal: F,
+) -> Result<usize, NavigationError>
+where
+    F: Fn(&str) -> bool,
+{
+    walk(document, start, is_goal).map(|path| path.len() - 1)
 }
 
-/*
-This method only works for specific inputs, namely, where the path starting at each node that ends
-with A goes through a single unique node that ends with Z, and the number of steps needed to reach
-that node for the first time is the same as the number of steps required to reach it every
-consecutive time. Both the example and the actual input satisfy this condition. It is easy to
-demonstrate that it is not true in the general case.
-*/
 #[aoc(day8, part1)]
 fn part1(document: &Document) -> usize {
+    steps_to_goal(document, "AAA", |node| node == "ZZZ").unwrap()
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct CycleAnalysis {
+    pub tail_len: usize,
+    pub period: usize,
+    pub tail_end_offsets: Vec<usize>,
+    pub cycle_end_offsets: Vec<usize>,
+}
+
+impl CycleAnalysis {
+    fn is_end_at(&self, step: usize) -> bool {
+        if step < self.tail_len {
+            self.tail_end_offsets.contains(&step)
+        } else {
+            let offset = self.tail_len + (step - self.tail_len) % self.period;
+            self.cycle_end_offsets.contains(&offset)
+        }
+    }
+}
+
+pub fn cycle_analysis<F>(
+    document: &Document,
+    start: &str,
+    is_end: F,
+) -> Result<CycleAnalysis, NavigationError>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut first_visits: HashMap<(&str, usize), usize> = HashMap::new();
+    let mut end_offsets = Vec::new();
+
+    let mut current_node = start;
     let mut steps = 0;
-    let mut current_node = "AAA";
 
-    for instruction in document.instructions.iter().cycle() {
-        steps += 1;
+    loop {
+        let instruction_index = steps % document.instructions.len();
 
-        let next_nodes = document.network.0.get(current_node).unwrap();
+        if let Some(&tail_len) = first_visits.get(&(current_node, instruction_index)) {
+            let (tail_end_offsets, cycle_end_offsets) = end_offsets
+                .into_iter()
+                .partition::<Vec<_>, _>(|&offset| offset < tail_len);
 
-        current_node = match instruction {
-            Left => &next_nodes.0,
-            Right => &next_nodes.1,
-        };
+            return Ok(CycleAnalysis {
+                tail_len,
+                period: steps - tail_len,
+                tail_end_offsets: tail_end_offsets
+                    .into_iter()
+                    .filter(|&offset| offset > 0)
+                    .collect(),
+                cycle_end_offsets,
+            });
+        }
 
-        if current_node == "ZZZ" {
-            return steps;
+        first_visits.insert((current_node, instruction_index), steps);
+
+        if is_end(current_node) {
+            end_offsets.push(steps);
         }
+
+        current_node = document
+            .network
+            .next(current_node, &document.instructions[instruction_index])?;
+        steps += 1;
     }
+}
 
-    unreachable!()
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (gcd, x, y) = extended_gcd(b, a % b);
+        (gcd, y, x - (a / b) * y)
+    }
 }
 
-#[aoc(day8, part2)]
-fn part2(document: &Document) -> usize {
-    let starting_nodes: Vec<_> = document
-        .network
-        .0
-        .keys()
-        .filter(|node| node.ends_with('A'))
-        .collect();
+fn combine(congruence1: (i128, i128), congruence2: (i128, i128)) -> Option<(i128, i128)> {
+    let (residue1, modulus1) = congruence1;
+    let (residue2, modulus2) = congruence2;
 
-    let mut steps = 1;
+    let (gcd, inverse, _) = extended_gcd(modulus1, modulus2);
 
-    for starting_node in starting_nodes.into_iter() {
-        let mut node_steps = 0;
-        let mut current_node = starting_node;
+    if (residue2 - residue1) % gcd != 0 {
+        return None;
+    }
 
-        for instruction in document.instructions.iter().cycle() {
-            node_steps += 1;
+    let modulus = modulus1 / gcd * modulus2;
+    let factor = ((residue2 - residue1) / gcd * inverse).rem_euclid(modulus2 / gcd);
 
-            let next_nodes = document.network.0.get(current_node).unwrap();
+    Some(((residue1 + modulus1 * factor).rem_euclid(modulus), modulus))
+}
 
-            current_node = match instruction {
-                Left => &next_nodes.0,
-                Right => &next_nodes.1,
-            };
+pub fn first_common_end(analyses: &[CycleAnalysis]) -> Result<usize, NavigationError> {
+    if analyses.is_empty() {
+        return Err(NavigationError::NoStart);
+    }
 
-            if current_node.ends_with('Z') {
-                steps = lcm(node_steps, steps);
-                break;
-            }
-        }
+    if analyses.iter().any(|analysis| {
+        analysis.tail_end_offsets.is_empty() && analysis.cycle_end_offsets.is_empty()
+    }) {
+        return Err(NavigationError::Unreachable);
+    }
+
+    let tail_candidate = analyses
+        .iter()
+        .flat_map(|analysis| analysis.tail_end_offsets.iter().copied())
+        .filter(|&step| analyses.iter().all(|analysis| analysis.is_end_at(step)))
+        .min();
+
+    let mut congruences = vec![(0, 1)];
+
+    for analysis in analyses.iter() {
+        congruences = congruences
+            .iter()
+            .flat_map(|&congruence| {
+                analysis
+                    .cycle_end_offsets
+                    .iter()
+                    .filter_map(move |&offset| {
+                        combine(congruence, (offset as i128, analysis.period as i128))
+                    })
+            })
+            .collect();
+        congruences.sort_unstable();
+        congruences.dedup();
     }
 
-    steps
+    let lower_bound = analyses
+        .iter()
+        .map(|analysis| analysis.tail_len)
+        .max()
+        .unwrap_or(0)
+        .max(1) as i128;
+
+    let cycle_candidate = congruences
+        .into_iter()
+        .map(|(residue, modulus)| {
+            (lower_bound + (residue - lower_bound).rem_euclid(modulus)) as usize
+        })
+        .min();
+
+    tail_candidate
+        .into_iter()
+        .chain(cycle_candidate)
+        .min()
+        .ok_or(NavigationError::NoCommonStep)
+}
+
+pub fn ghost_steps<F, G>(
+    document: &Document,
+    is_start: F,
+    is_goal: G,
+) -> Result<usize, NavigationError>
+where
+    F: Fn(&str) -> bool,
+    G: Fn(&str) -> bool,
+{
+    let analyses = document
+        .network
+        .0
+        .keys()
+        .filter(|node| is_start(node))
+        .map(|node| cycle_analysis(document, node, &is_goal))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    first_common_end(&analyses)
+}
+
+#[aoc(day8, part2)]
+fn part2(document: &Document) -> usize {
+    ghost_steps(
+        document,
+        |node| node.ends_with('A'),
+        |node| node.ends_with('Z'),
+    )
+    .unwrap()
 }
 
 #[cfg(test)]
@@ -148,4 +377,143 @@ XXX = (XXX, XXX)";
     fn part2_example() {
         assert_eq!(part2(&parse_input(PART_2_TEST_INPUT)), 6);
     }
+
+    #[test]
+    fn cycle_analysis_with_tail() {
+        let document = parse_input(
+            r"L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11C, 11C)
+11C = (11Z, 11Z)
+22A = (22Z, 22Z)
+22Z = (22D, 22D)
+22D = (22E, 22E)
+22E = (22Z, 22Z)",
+        );
+
+        let analyses = ["11A", "22A"]
+            .map(|start| cycle_analysis(&document, start, |node| node.ends_with('Z')).unwrap());
+
+        assert_eq!(
+            analyses,
+            [
+                CycleAnalysis {
+                    tail_len: 2,
+                    period: 2,
+                    tail_end_offsets: vec![],
+                    cycle_end_offsets: vec![2],
+                },
+                CycleAnalysis {
+                    tail_len: 1,
+                    period: 3,
+                    tail_end_offsets: vec![],
+                    cycle_end_offsets: vec![1],
+                },
+            ]
+        );
+        assert_eq!(first_common_end(&analyses), Ok(4));
+        assert_eq!(part2(&document), 4);
+    }
+
+    #[test]
+    fn no_common_end() {
+        let document = parse_input(
+            r"L
+
+11A = (11Z, 11Z)
+11Z = (11B, 11B)
+11B = (11Z, 11Z)
+22A = (22B, 22B)
+22B = (22Z, 22Z)
+22Z = (22B, 22B)",
+        );
+
+        let analyses = ["11A", "22A"]
+            .map(|start| cycle_analysis(&document, start, |node| node.ends_with('Z')).unwrap());
+
+        assert_eq!(
+            first_common_end(&analyses),
+            Err(NavigationError::NoCommonStep)
+        );
+    }
+
+    #[test]
+    fn unreachable_and_missing_goals() {
+        let document = parse_input(PART_1_TEST_INPUT_1);
+
+        assert_eq!(
+            steps_to_goal(&document, "BBB", |node| node == "ZZZ"),
+            Err(NavigationError::Unreachable)
+        );
+        assert_eq!(steps_to_goal(&document, "AAA", |node| node == "CCC"), Ok(1));
+        assert_eq!(
+            steps_to_goal(&document, "AAA", |node| node == "AAA"),
+            Err(NavigationError::Unreachable)
+        );
+        assert_eq!(
+            steps_to_goal(&document, "QQQ", |node| node == "ZZZ"),
+            Err(NavigationError::MissingNode(String::from("QQQ")))
+        );
+        assert_eq!(
+            ghost_steps(&document, |node| node == "AAA", |node| node == "GGG"),
+            Err(NavigationError::Unreachable)
+        );
+        assert_eq!(
+            ghost_steps(&document, |node| node.ends_with('Q'), |node| node == "ZZZ"),
+            Err(NavigationError::NoStart)
+        );
+        assert_eq!(first_common_end(&[]), Err(NavigationError::NoStart));
+    }
+
+    #[test]
+    fn network_to_dot() {
+        let document = parse_input(PART_1_TEST_INPUT_2);
+        let path = walk(&document, "AAA", |node| node == "ZZZ").unwrap();
+
+        assert_eq!(path, ["AAA", "BBB", "AAA", "BBB", "AAA", "BBB", "ZZZ"]);
+        assert_eq!(
+            document.network.to_dot(&path),
+            r#"digraph network {
+    "AAA" [label="AAA" color=red];
+    "BBB" [label="BBB" color=red];
+    "ZZZ" [label="ZZZ" color=red];
+    "AAA" -> "BBB" [label="L" color=red];
+    "AAA" -> "BBB" [label="R" color=red];
+    "BBB" -> "AAA" [label="L" color=red];
+    "BBB" -> "ZZZ" [label="R" color=red];
+    "ZZZ" -> "ZZZ" [label="L"];
+    "ZZZ" -> "ZZZ" [label="R"];
+}"#
+        );
+    }
+
+    #[test]
+    fn network_to_dot_quotes_ids() {
+        let document = parse_input(PART_2_TEST_INPUT);
+        let path = walk(&document, "11A", |node| node.ends_with('Z')).unwrap();
+        let dot = document.network.to_dot(&path);
+
+        assert!(dot.contains(r#"    "11A" [label="11A" color=red];"#));
+        assert!(dot.contains(r#"    "11A" -> "11B" [label="L" color=red];"#));
+        assert!(dot.contains(r#"    "22C" -> "22Z" [label="L"];"#));
+        assert!(dot
+            .lines()
+            .skip(1)
+            .filter(|line| *line != "}")
+            .all(|line| line.trim_start().starts_with('"')));
+    }
+
+    #[test]
+    fn document_from_str() {
+        let document = PART_1_TEST_INPUT_1.parse::<Document>().unwrap();
+
+        assert_eq!(steps_to_goal(&document, "AAA", |node| node == "ZZZ"), Ok(2));
+        assert!(document
+            .network()
+            .to_dot(&[])
+            .starts_with("digraph network {"));
+        assert!("LR\n\nAAA = BBB".parse::<Document>().is_err());
+    }
 }
@@ -1,4 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use num::rational::BigRational;
+use num::{BigInt, ToPrimitive, Zero};
 
 #[aoc_generator(day9)]
 fn parse_input(report: &str) -> Vec<Vec<i32>> {
@@ -8,11 +10,11 @@ fn parse_input(report: &str) -> Vec<Vec<i32>> {
     parser.parse(report).unwrap()
 }
 
-fn derivatives(history: &[i32]) -> Vec<Vec<i32>> {
+fn derivatives(history: &[i128]) -> Vec<Vec<i128>> {
     let mut derivatives = vec![history.to_vec()];
 
     loop {
-        let differences: Vec<i32> = derivatives
+        let differences: Vec<i128> = derivatives
             .last()
             .unwrap()
             .windows(2)
@@ -32,34 +34,115 @@ fn derivatives(history: &[i32]) -> Vec<Vec<i32>> {
     derivatives
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum SequenceError {
+    Empty,
+    NotPolynomial,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Polynomial(Vec<BigInt>);
+
+impl Polynomial {
+    pub fn fit(history: &[i32]) -> Result<Self, SequenceError> {
+        if history.is_empty() {
+            return Err(SequenceError::Empty);
+        }
+
+        let history = history
+            .iter()
+            .map(|value| *value as i128)
+            .collect::<Vec<_>>();
+        let mut derivatives = derivatives(&history);
+
+        if history.iter().all(|value| *value == 0) {
+            derivatives.truncate(1);
+        } else if derivatives.last().unwrap().iter().all(|step| *step == 0)
+            && !derivatives.last().unwrap().is_empty()
+        {
+            derivatives.pop();
+        } else {
+            return Err(SequenceError::NotPolynomial);
+        }
+
+        Ok(Polynomial(
+            derivatives
+                .iter()
+                .map(|steps| BigInt::from(steps[0]))
+                .collect(),
+        ))
+    }
+
+    pub fn degree(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    pub fn coefficients(&self) -> Vec<BigRational> {
+        let mut coefficients = vec![BigRational::zero(); self.0.len()];
+        let mut falling_factorial = vec![BigInt::from(1)];
+        let mut factorial = BigInt::from(1);
+
+        for (k, difference) in self.0.iter().enumerate() {
+            if k > 0 {
+                let mut next_falling_factorial = vec![BigInt::zero(); k + 1];
+
+                for (power, coefficient) in falling_factorial.iter().enumerate() {
+                    next_falling_factorial[power + 1] += coefficient;
+                    next_falling_factorial[power] -= coefficient * BigInt::from(k - 1);
+                }
+
+                falling_factorial = next_falling_factorial;
+                factorial *= BigInt::from(k);
+            }
+
+            for (power, coefficient) in falling_factorial.iter().enumerate() {
+                coefficients[power] +=
+                    BigRational::new(difference * coefficient, factorial.clone());
+            }
+        }
+
+        coefficients
+    }
+
+    pub fn value_at(&self, position: i128) -> BigInt {
+        let position = BigInt::from(position);
+        let mut binomial = BigInt::from(1);
+        let mut value = BigInt::zero();
+
+        for (k, difference) in self.0.iter().enumerate() {
+            if k > 0 {
+                binomial = binomial * (&position - BigInt::from(k - 1)) / BigInt::from(k);
+            }
+
+            value += difference * &binomial;
+        }
+
+        value
+    }
+}
+
 #[aoc(day9, part1)]
-fn part1(report: &[Vec<i32>]) -> i32 {
+fn part1(report: &[Vec<i32>]) -> i64 {
     report
         .iter()
         .map(|history| {
-            derivatives(history)
-                .iter()
-                .map(|steps| *steps.last().unwrap())
-                .rev()
-                .reduce(|extrapolation, last_difference| extrapolation + last_difference)
+            Polynomial::fit(history)
                 .unwrap()
+                .value_at(history.len() as i128)
         })
-        .sum()
+        .sum::<BigInt>()
+        .to_i64()
+        .unwrap()
 }
 
 #[aoc(day9, part2)]
-fn part2(report: &[Vec<i32>]) -> i32 {
+fn part2(report: &[Vec<i32>]) -> i64 {
     report
         .iter()
-        .map(|history| {
-            derivatives(history)
-                .iter()
-                .map(|steps| *steps.first().unwrap())
-                .rev()
-                .reduce(|extrapolation, first_difference| first_difference - extrapolation)
-                .unwrap()
-        })
-        .sum()
+        .map(|history| Polynomial::fit(history).unwrap().value_at(-1))
+        .sum::<BigInt>()
+        .to_i64()
+        .unwrap()
 }
 
 #[cfg(test)]
@@ -79,4 +162,60 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 2);
     }
+
+    #[test]
+    fn polynomial_example() {
+        let report = parse_input(TEST_INPUT);
+        let polynomials = report
+            .iter()
+            .map(|history| Polynomial::fit(history).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            polynomials
+                .iter()
+                .map(|polynomial| polynomial.degree())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        let rational = |numerator: i64, denominator: i64| {
+            BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+        };
+
+        assert_eq!(
+            polynomials[0].coefficients(),
+            vec![rational(0, 1), rational(3, 1)]
+        );
+        assert_eq!(
+            polynomials[1].coefficients(),
+            vec![rational(1, 1), rational(3, 2), rational(1, 2)]
+        );
+
+        assert_eq!(
+            polynomials[0].value_at(1_000_000_000),
+            BigInt::from(3_000_000_000i64)
+        );
+        assert_eq!(
+            polynomials[1].value_at(1_000_000_000),
+            BigInt::from(500_000_001_500_000_001i64)
+        );
+        assert_eq!(polynomials[2].value_at(-1), BigInt::from(5));
+        assert_eq!(polynomials[2].value_at(-10), BigInt::from(-460));
+    }
+
+    #[test]
+    fn polynomial_errors() {
+        assert_eq!(Polynomial::fit(&[]), Err(SequenceError::Empty));
+        assert_eq!(
+            Polynomial::fit(&[1, 2, 4, 8, 16]),
+            Err(SequenceError::NotPolynomial)
+        );
+        assert_eq!(Polynomial::fit(&[7]), Err(SequenceError::NotPolynomial));
+        assert_eq!(Polynomial::fit(&[7, 7]).unwrap().degree(), 0);
+        assert_eq!(
+            Polynomial::fit(&[0, 0, 0]).unwrap().value_at(-5),
+            BigInt::zero()
+        );
+    }
 }
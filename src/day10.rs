@@ -1,112 +1,228 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::{fs, io};
 
-type Position = (i32, i32);
-type Direction = (i32, i32);
+pub type Position = (i32, i32);
+pub type Direction = (i32, i32);
 type Pipe = [Direction; 2];
 
-struct Sketch {
+pub struct Sketch {
     starting_position: Position,
+    starting_tile: Option<Pipe>,
     pipes: HashMap<Position, Pipe>,
     bottom_right_position: Position,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum StartError {
+    InvalidTile(char),
+    Disconnected(Vec<Direction>),
+    Ambiguous(Vec<Direction>),
+}
+
+fn pipe(tile: char) -> Option<Pipe> {
+    match tile {
+        '|' => Some([(0, -1), (0, 1)]),
+        '-' => Some([(-1, 0), (1, 0)]),
+        'L' => Some([(0, -1), (1, 0)]),
+        'J' => Some([(0, -1), (-1, 0)]),
+        '7' => Some([(-1, 0), (0, 1)]),
+        'F' => Some([(1, 0), (0, 1)]),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SketchError {
+    InvalidTile(Position, char),
+    MissingStart,
+}
+
+impl FromStr for Sketch {
+    type Err = SketchError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut starting_position = None;
+        let mut pipes = HashMap::new();
+
+        for (y, line) in input.lines().enumerate() {
+            for (x, tile) in line.chars().enumerate() {
+                let position = (x as i32, y as i32);
+
+                match tile {
+                    'S' => starting_position = Some(position),
+                    '.' => (),
+                    _ => {
+                        pipes.insert(
+                            position,
+                            pipe(tile).ok_or(SketchError::InvalidTile(position, tile))?,
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Sketch {
+            starting_position: starting_position.ok_or(SketchError::MissingStart)?,
+            starting_tile: None,
+            pipes,
+            bottom_right_position: (
+                input.lines().next().unwrap_or("").chars().count() as i32 - 1,
+                input.lines().count() as i32 - 1,
+            ),
+        })
+    }
+}
+
 #[aoc_generator(day10)]
 fn parse_input(input: &str) -> Sketch {
-    let mut sketch = Sketch {
-        starting_position: (0, 0),
-        pipes: HashMap::new(),
-        bottom_right_position: (
-            input.lines().next().unwrap().chars().count() as i32 - 1,
-            input.lines().count() as i32 - 1,
-        ),
+    input.parse().unwrap()
+}
+
+impl Sketch {
+    fn connects_to_start(&self, direction: &Direction) -> bool {
+        let pipe_position = (
+            self.starting_position.0 + direction.0,
+            self.starting_position.1 + direction.1,
+        );
+
+        self.pipes
+            .get(&pipe_position)
+            .is_some_and(|pipe| pipe.contains(&(-direction.0, -direction.1)))
+    }
+
+    pub fn with_starting_tile(mut self, tile: char) -> Result<Self, StartError> {
+        let starting_tile = pipe(tile).ok_or(StartError::InvalidTile(tile))?;
+        let connected_directions = starting_tile
+            .into_iter()
+            .filter(|direction| self.connects_to_start(direction))
+            .collect::<Vec<_>>();
+
+        if connected_directions.len() < 2 {
+            return Err(StartError::Disconnected(connected_directions));
+        }
+
+        self.starting_tile = Some(starting_tile);
+
+        Ok(self)
+    }
+}
+
+pub fn starting_pipe(sketch: &Sketch) -> Result<Pipe, StartError> {
+    if let Some(starting_tile) = sketch.starting_tile {
+        return Ok(starting_tile);
+    }
+
+    let starting_directions = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+        .into_iter()
+        .filter(|direction| sketch.connects_to_start(direction))
+        .collect::<Vec<_>>();
+
+    match starting_directions.len() {
+        0 | 1 => Err(StartError::Disconnected(starting_directions)),
+        2 => Ok(starting_directions.try_into().unwrap()),
+        _ => Err(StartError::Ambiguous(starting_directions)),
+    }
+}
+
+fn pipe_at(sketch: &Sketch, position: Position) -> Option<Pipe> {
+    if position == sketch.starting_position {
+        starting_pipe(sketch).ok()
+    } else {
+        sketch.pipes.get(&position).copied()
+    }
+}
+
+fn walk(
+    sketch: &Sketch,
+    start: Position,
+    visited: &HashSet<Position>,
+) -> Result<Vec<Position>, Vec<Position>> {
+    let mut path = vec![start];
+    let mut current_position = start;
+    let mut direction = match pipe_at(sketch, start) {
+        Some(pipe) => pipe[0],
+        None => return Err(path),
     };
 
-    input.lines().enumerate().for_each(|(y, line)| {
-        line.chars().enumerate().for_each(|(x, tile)| match tile {
-            'S' => sketch.starting_position = (x as i32, y as i32),
-            '.' => (),
-            _ => {
-                sketch.pipes.insert(
-                    (x as i32, y as i32),
-                    match tile {
-                        '|' => [(0, -1), (0, 1)],
-                        '-' => [(-1, 0), (1, 0)],
-                        'L' => [(0, -1), (1, 0)],
-                        'J' => [(0, -1), (-1, 0)],
-                        '7' => [(-1, 0), (0, 1)],
-                        'F' => [(1, 0), (0, 1)],
-                        _ => unreachable!(),
-                    },
-                );
-            }
-        })
-    });
-
-    sketch
-}
-
-fn starting_pipe(sketch: &Sketch) -> Pipe {
-    let starting_position = sketch.starting_position;
-    let mut starting_directions = Vec::with_capacity(2);
-
-    for (x, y) in [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ] {
-        let pipe_position = (starting_position.0 + x, starting_position.1 + y);
-
-        if let Some(pipe) = sketch.pipes.get(&pipe_position) {
-            for direction in pipe.iter() {
-                if (pipe_position.0 + direction.0, pipe_position.1 + direction.1)
-                    == starting_position
-                {
-                    starting_directions.push((-direction.0, -direction.1));
-                    break;
-                }
+    loop {
+        let next_position = (
+            current_position.0 + direction.0,
+            current_position.1 + direction.1,
+        );
+        let backwards = (-direction.0, -direction.1);
+
+        let next_pipe = match pipe_at(sketch, next_position) {
+            Some(next_pipe)
+                if next_pipe.contains(&backwards) && !visited.contains(&next_position) =>
+            {
+                next_pipe
             }
+            _ => return Err(path),
+        };
 
-            if starting_directions.len() == 2 {
-                break;
-            }
+        if next_position == start {
+            return Ok(path);
         }
+
+        path.push(next_position);
+        current_position = next_position;
+        direction = *next_pipe.iter().find(|d| **d != backwards).unwrap();
     }
+}
+
+pub fn loop_path(sketch: &Sketch, start: Position) -> Option<Vec<Position>> {
+    walk(sketch, start, &HashSet::new()).ok()
+}
 
-    starting_directions.try_into().unwrap()
+pub fn main_loop_path(sketch: &Sketch) -> Vec<Position> {
+    loop_path(sketch, sketch.starting_position).unwrap()
 }
 
 fn main_loop(sketch: &Sketch) -> HashSet<Position> {
-    let mut current_position = sketch.starting_position;
-    let mut main_loop = HashSet::from([current_position]);
-
-    'main_loop: loop {
-        if let Some(pipe) = sketch
-            .pipes
-            .get(&current_position)
-            .or(Some(&starting_pipe(sketch)))
-        {
-            for direction in pipe {
-                let adjacent_pipe_position = (
-                    current_position.0 + direction.0,
-                    current_position.1 + direction.1,
-                );
-
-                if !main_loop.contains(&adjacent_pipe_position) {
-                    current_position = adjacent_pipe_position;
-                    main_loop.insert(current_position);
-                    continue 'main_loop;
-                }
-            }
+    main_loop_path(sketch).into_iter().collect()
+}
+
+pub fn closed_loops(sketch: &Sketch) -> Vec<Vec<Position>> {
+    let mut positions = sketch
+        .pipes
+        .keys()
+        .copied()
+        .chain([sketch.starting_position])
+        .collect::<Vec<_>>();
+    positions.sort_unstable_by_key(|&(x, y)| (y, x));
+
+    let mut visited = HashSet::new();
+    let mut loops = Vec::new();
+
+    for position in positions {
+        if visited.contains(&position) {
+            continue;
+        }
 
-            return main_loop;
+        match walk(sketch, position, &visited) {
+            Ok(path) => {
+                visited.extend(path.iter().copied());
+                loops.push(path);
+            }
+            Err(path) => visited.extend(path),
         }
     }
+
+    loops
+}
+
+pub fn interior_area(path: &[Position]) -> usize {
+    let double_area = path
+        .iter()
+        .zip(path.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+
+    (double_area + 2 - path.len()) / 2
 }
 
 #[aoc(day10, part1)]
@@ -114,11 +230,9 @@ fn part1(sketch: &Sketch) -> usize {
     main_loop(sketch).len() / 2
 }
 
-#[aoc(day10, part2)]
-fn part2(sketch: &Sketch) -> usize {
-    let main_loop = main_loop(sketch);
-    let starting_pipe = starting_pipe(sketch);
-    let mut inside_count = 0;
+fn inside_tiles(sketch: &Sketch, main_loop: &HashSet<Position>) -> HashSet<Position> {
+    let starting_pipe = starting_pipe(sketch).unwrap();
+    let mut inside_tiles = HashSet::new();
 
     for y in 0..=sketch.bottom_right_position.1 {
         let mut outside = true;
@@ -131,12 +245,73 @@ fn part2(sketch: &Sketch) -> usize {
                     outside = !outside;
                 }
             } else if !outside {
-                inside_count += 1;
+                inside_tiles.insert((x, y));
             }
         }
     }
 
-    inside_count
+    inside_tiles
+}
+
+#[aoc(day10, part2)]
+fn part2(sketch: &Sketch) -> usize {
+    inside_tiles(sketch, &main_loop(sketch)).len()
+}
+
+fn box_drawing(pipe: &Pipe) -> char {
+    let connects = |direction: Direction| pipe.contains(&direction);
+
+    match (
+        connects((0, -1)),
+        connects((0, 1)),
+        connects((-1, 0)),
+        connects((1, 0)),
+    ) {
+        (true, true, _, _) => '│',
+        (_, _, true, true) => '─',
+        (true, _, _, true) => '└',
+        (true, _, true, _) => '┘',
+        (_, true, true, _) => '┐',
+        (_, true, _, true) => '┌',
+        _ => unreachable!(),
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RenderStyle {
+    Plain,
+    Terminal,
+}
+
+pub fn render(sketch: &Sketch, style: RenderStyle) -> String {
+    let main_loop = main_loop(sketch);
+    let inside_tiles = inside_tiles(sketch, &main_loop);
+
+    let (inside, outside) = match style {
+        RenderStyle::Plain => ("I", "O"),
+        RenderStyle::Terminal => ("\x1b[1;32mI\x1b[0m", "\x1b[2mO\x1b[0m"),
+    };
+
+    (0..=sketch.bottom_right_position.1)
+        .map(|y| {
+            (0..=sketch.bottom_right_position.0)
+                .map(|x| {
+                    if main_loop.contains(&(x, y)) {
+                        box_drawing(&pipe_at(sketch, (x, y)).unwrap()).to_string()
+                    } else if inside_tiles.contains(&(x, y)) {
+                        inside.to_string()
+                    } else {
+                        outside.to_string()
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn render_to_file<P: AsRef<Path>>(sketch: &Sketch, path: P) -> io::Result<()> {
+    fs::write(path, render(sketch, RenderStyle::Plain) + "\n")
 }
 
 #[cfg(test)]
@@ -226,4 +401,173 @@ L7JLJL-JLJLJL--JLJ.L";
     fn part2_example4() {
         assert_eq!(part2(&parse_input(PART_2_TEST_INPUT_4)), 10);
     }
+
+    #[test]
+    fn interior_area_examples() {
+        for (input, expected) in [
+            (PART_2_TEST_INPUT_1, 4),
+            (PART_2_TEST_INPUT_2, 4),
+            (PART_2_TEST_INPUT_3, 8),
+            (PART_2_TEST_INPUT_4, 10),
+        ] {
+            assert_eq!(
+                interior_area(&main_loop_path(&parse_input(input))),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn main_loop_path_example() {
+        assert_eq!(
+            main_loop_path(&parse_input(PART_1_TEST_INPUT_1)),
+            vec![
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 3),
+                (3, 3),
+                (3, 2),
+                (3, 1),
+                (2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_loops_example() {
+        let sketch = parse_input(
+            r"F7.....
+LJ.S-7.
+...|.|.
+...L-J.
+F-7..F-
+|.|..|.
+L-J..L7",
+        );
+
+        let loops = closed_loops(&sketch);
+
+        assert_eq!(
+            loops
+                .iter()
+                .map(|path| (path[0], path.len(), interior_area(path)))
+                .collect::<Vec<_>>(),
+            vec![((0, 0), 4, 0), ((3, 1), 8, 1), ((0, 4), 8, 1)]
+        );
+    }
+
+    #[test]
+    fn closed_loops_long_open_chain() {
+        let chain = "-".repeat(100_000);
+        let sketch = format!(
+            "S{chain}\nF7{}\nLJ{}",
+            ".".repeat(99_999),
+            ".".repeat(99_999)
+        )
+        .parse::<Sketch>()
+        .unwrap();
+
+        assert_eq!(
+            closed_loops(&sketch)
+                .iter()
+                .map(|path| (path[0], path.len()))
+                .collect::<Vec<_>>(),
+            vec![((0, 1), 4)]
+        );
+    }
+
+    #[test]
+    fn sketch_from_str() {
+        let sketch = PART_1_TEST_INPUT_1.parse::<Sketch>().unwrap();
+
+        assert_eq!(main_loop_path(&sketch).len(), 8);
+        assert_eq!(
+            ".S-7.\n.|X|.".parse::<Sketch>().err(),
+            Some(SketchError::InvalidTile((2, 1), 'X'))
+        );
+        assert_eq!(
+            "F7\nLJ".parse::<Sketch>().err(),
+            Some(SketchError::MissingStart)
+        );
+    }
+
+    #[test]
+    fn render_example() {
+        let sketch = parse_input(PART_2_TEST_INPUT_2);
+
+        assert_eq!(
+            render(&sketch, RenderStyle::Plain),
+            r"OOOOOOOOOO
+O┌──────┐O
+O│┌────┐│O
+O││OOOO││O
+O││OOOO││O
+O│└─┐┌─┘│O
+O│II││II│O
+O└──┘└──┘O
+OOOOOOOOOO"
+        );
+
+        let rendering = render(&parse_input(PART_1_TEST_INPUT_2), RenderStyle::Terminal);
+
+        assert_eq!(
+            rendering.lines().next().unwrap(),
+            "\x1b[2mO\x1b[0m\x1b[2mO\x1b[0m┌┐\x1b[2mO\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn render_to_file_example() {
+        let sketch = parse_input(PART_2_TEST_INPUT_1);
+        let path = std::env::temp_dir().join("advent_of_code_2023_day10_render.txt");
+
+        render_to_file(&sketch, &path).unwrap();
+
+        let rendering = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(rendering, render(&sketch, RenderStyle::Plain) + "\n");
+        assert_eq!(rendering.matches('I').count(), 4);
+    }
+
+    #[test]
+    fn starting_pipe_errors() {
+        let ambiguous_sketch = parse_input(
+            r".|...
+-S-7.
+.L-J.",
+        );
+
+        assert_eq!(
+            starting_pipe(&ambiguous_sketch),
+            Err(StartError::Ambiguous(vec![
+                (0, -1),
+                (0, 1),
+                (-1, 0),
+                (1, 0)
+            ]))
+        );
+        assert!(closed_loops(&ambiguous_sketch).is_empty());
+
+        let sketch = ambiguous_sketch.with_starting_tile('F').unwrap();
+
+        assert_eq!(starting_pipe(&sketch), Ok([(1, 0), (0, 1)]));
+        assert_eq!(part1(&sketch), 3);
+
+        assert_eq!(
+            starting_pipe(&parse_input("F-.\n.S-\n...")),
+            Err(StartError::Disconnected(vec![(1, 0)]))
+        );
+        assert!(parse_input("S").with_starting_tile('X').is_err());
+
+        for (tile, connected_directions) in
+            [('J', vec![]), ('L', vec![(1, 0)]), ('-', vec![(1, 0)])]
+        {
+            assert_eq!(
+                parse_input("F-.\n.S-\n...").with_starting_tile(tile).err(),
+                Some(StartError::Disconnected(connected_directions))
+            );
+        }
+    }
 }
@@ -1,10 +1,18 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::cmp::Reverse;
+use std::fmt::Write;
 
-type Coordinates = (i64, i64);
+pub type Coordinates = (i64, i64);
+
+pub struct Image {
+    pub galaxies: Vec<Coordinates>,
+    pub width: i64,
+    pub height: i64,
+}
 
 #[aoc_generator(day11)]
-fn parse_input(image: &str) -> Vec<Coordinates> {
-    image
+fn parse_input(image: &str) -> Image {
+    let galaxies = image
         .lines()
         .enumerate()
         .flat_map(move |(y, line)| {
@@ -16,56 +24,202 @@ fn parse_input(image: &str) -> Vec<Coordinates> {
                 }
             })
         })
-        .collect()
+        .collect();
+
+    Image {
+        galaxies,
+        width: image.lines().map(|line| line.len()).max().unwrap_or(0) as i64,
+        height: image.lines().count() as i64,
+    }
+}
+
+fn expand_axis(values: &[i64], expansion_factor: i64) -> Option<Vec<i64>> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&index| values[index]);
+
+    if expansion_factor < 1 {
+        return None;
+    }
+
+    let growth = expansion_factor - 1;
+    let mut expanded_values = vec![0; values.len()];
+
+    let mut empty_count: i64 = 0;
+    let mut last_non_empty = -1;
+
+    for index in order {
+        empty_count += i64::max(values[index] - last_non_empty - 1, 0);
+        last_non_empty = values[index];
+        expanded_values[index] = values[index].checked_add(empty_count.checked_mul(growth)?)?;
+    }
+
+    Some(expanded_values)
+}
+
+fn axis_distance_sum(values: &[i64]) -> Option<i64> {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+
+    let mut prefix_sum: i64 = 0;
+    let mut distance_sum: i64 = 0;
+
+    for (index, value) in values.into_iter().enumerate() {
+        distance_sum =
+            distance_sum.checked_add(value.checked_mul(index as i64)?.checked_sub(prefix_sum)?)?;
+        prefix_sum = prefix_sum.checked_add(value)?;
+    }
+
+    Some(distance_sum)
+}
+
+pub struct ExpandedImage {
+    galaxies: Vec<Coordinates>,
+    width: i64,
+    height: i64,
+}
+
+fn expand_axis_with_size(
+    values: impl Iterator<Item = i64>,
+    size: i64,
+    expansion_factor: i64,
+) -> Option<(Vec<i64>, i64)> {
+    let mut values = values.chain([size]).collect::<Vec<_>>();
+    values = expand_axis(&values, expansion_factor)?;
+    let size = values.pop()?;
+
+    Some((values, size))
+}
+
+impl ExpandedImage {
+    pub fn new(image: &Image, column_factor: i64, row_factor: i64) -> Option<Self> {
+        if !image
+            .galaxies
+            .iter()
+            .all(|&(x, y)| (0..image.width).contains(&x) && (0..image.height).contains(&y))
+        {
+            return None;
+        }
+
+        let (columns, width) = expand_axis_with_size(
+            image.galaxies.iter().map(|coordinates| coordinates.0),
+            image.width,
+            column_factor,
+        )?;
+        let (rows, height) = expand_axis_with_size(
+            image.galaxies.iter().map(|coordinates| coordinates.1),
+            image.height,
+            row_factor,
+        )?;
+
+        Some(ExpandedImage {
+            galaxies: columns.into_iter().zip(rows).collect(),
+            width,
+            height,
+        })
+    }
+
+    pub fn distance(&self, galaxy1: usize, galaxy2: usize) -> Option<i64> {
+        let coordinates1 = *self.galaxies.get(galaxy1)?;
+        let coordinates2 = *self.galaxies.get(galaxy2)?;
+
+        coordinates1
+            .0
+            .abs_diff(coordinates2.0)
+            .checked_add(coordinates1.1.abs_diff(coordinates2.1))?
+            .try_into()
+            .ok()
+    }
+
+    pub fn sum_of_distances(&self) -> Option<i64> {
+        let columns = self
+            .galaxies
+            .iter()
+            .map(|coordinates| coordinates.0)
+            .collect::<Vec<_>>();
+        let rows = self
+            .galaxies
+            .iter()
+            .map(|coordinates| coordinates.1)
+            .collect::<Vec<_>>();
+
+        axis_distance_sum(&columns)?.checked_add(axis_distance_sum(&rows)?)
+    }
 }
 
-fn sum_of_distances(image: &[Coordinates], expansion_factor: i64) -> i64 {
-    let mut expanded_image = image.to_vec();
+const MAX_RENDER_AREA: i64 = 1 << 20;
 
-    expanded_image.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+impl ExpandedImage {
+    fn distances_from(&self, galaxy: usize) -> Option<Vec<(usize, i64)>> {
+        (0..self.galaxies.len())
+            .filter(|other_galaxy| *other_galaxy != galaxy)
+            .map(|other_galaxy| Some((other_galaxy, self.distance(galaxy, other_galaxy)?)))
+            .collect()
+    }
 
-    let mut empty_columns_count = 0;
-    let mut last_non_empty_column = 0;
+    pub fn nearest(&self, galaxy: usize) -> Option<(usize, i64)> {
+        self.distances_from(galaxy)?
+            .into_iter()
+            .min_by_key(|&(other_galaxy, distance)| (distance, other_galaxy))
+    }
 
-    for coordinates in expanded_image.iter_mut() {
-        empty_columns_count += i64::max(coordinates.0 - last_non_empty_column - 1, 0);
-        last_non_empty_column = coordinates.0;
-        coordinates.0 += empty_columns_count * (expansion_factor - 1);
+    pub fn farthest(&self, galaxy: usize) -> Option<(usize, i64)> {
+        self.distances_from(galaxy)?
+            .into_iter()
+            .max_by_key(|&(other_galaxy, distance)| (distance, Reverse(other_galaxy)))
     }
 
-    expanded_image.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+    pub fn distance_matrix_csv(&self) -> Option<String> {
+        let mut csv = String::new();
+
+        for galaxy in 0..self.galaxies.len() {
+            write!(csv, ",{galaxy}").unwrap();
+        }
 
-    let mut empty_rows_count = 0;
-    let mut last_non_empty_row = 0;
+        for galaxy in 0..self.galaxies.len() {
+            write!(csv, "\n{galaxy}").unwrap();
 
-    for coordinates in expanded_image.iter_mut() {
-        empty_rows_count += i64::max(coordinates.1 - last_non_empty_row - 1, 0);
-        last_non_empty_row = coordinates.1;
-        coordinates.1 += empty_rows_count * (expansion_factor - 1);
+            for other_galaxy in 0..self.galaxies.len() {
+                write!(csv, ",{}", self.distance(galaxy, other_galaxy)?).unwrap();
+            }
+        }
+
+        Some(csv)
     }
 
-    let mut distance_sum = 0;
+    pub fn render(&self) -> Option<String> {
+        if self.width.checked_mul(self.height)? > MAX_RENDER_AREA {
+            return None;
+        }
 
-    for i in 0..expanded_image.len() - 1 {
-        for j in i + 1..expanded_image.len() {
-            let coordinates_i = expanded_image[i];
-            let coordinates_j = expanded_image[j];
+        let mut pixels = vec![vec!['.'; self.width as usize]; self.height as usize];
 
-            distance_sum += (coordinates_j.0 - coordinates_i.0).abs()
-                + (coordinates_j.1 - coordinates_i.1).abs();
+        for (x, y) in self.galaxies.iter() {
+            pixels[*y as usize][*x as usize] = '#';
         }
+
+        Some(
+            pixels
+                .into_iter()
+                .map(|row| row.into_iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
     }
+}
 
-    distance_sum
+fn sum_of_distances(image: &Image, expansion_factor: i64) -> i64 {
+    ExpandedImage::new(image, expansion_factor, expansion_factor)
+        .and_then(|expanded_image| expanded_image.sum_of_distances())
+        .unwrap()
 }
 
 #[aoc(day11, part1)]
-fn part1(image: &[Coordinates]) -> i64 {
+fn part1(image: &Image) -> i64 {
     sum_of_distances(image, 2)
 }
 
 #[aoc(day11, part2)]
-fn part2(image: &[Coordinates]) -> i64 {
+fn part2(image: &Image) -> i64 {
     sum_of_distances(image, 1_000_000)
 }
 
@@ -98,4 +252,167 @@ mod tests {
     fn part2_example2() {
         assert_eq!(sum_of_distances(&parse_input(TEST_INPUT), 100), 8_410);
     }
+
+    #[test]
+    fn distance_example() {
+        let expanded_image = ExpandedImage::new(&parse_input(TEST_INPUT), 2, 2).unwrap();
+
+        assert_eq!(expanded_image.distance(4, 8), Some(9));
+        assert_eq!(expanded_image.distance(0, 6), Some(15));
+        assert_eq!(expanded_image.distance(2, 5), Some(17));
+        assert_eq!(expanded_image.distance(7, 8), Some(5));
+    }
+
+    #[test]
+    fn per_axis_expansion_factors() {
+        let image = parse_input(TEST_INPUT);
+
+        let brute_force_sum = |column_factor, row_factor| {
+            let expanded_image = ExpandedImage::new(&image, column_factor, row_factor).unwrap();
+
+            (0..image.galaxies.len())
+                .flat_map(|i| (i + 1..image.galaxies.len()).map(move |j| (i, j)))
+                .map(|(i, j)| expanded_image.distance(i, j).unwrap())
+                .sum::<i64>()
+        };
+
+        for (column_factor, row_factor) in [(1, 1), (2, 10), (100, 1), (7, 3)] {
+            assert_eq!(
+                ExpandedImage::new(&image, column_factor, row_factor)
+                    .unwrap()
+                    .sum_of_distances(),
+                Some(brute_force_sum(column_factor, row_factor))
+            );
+        }
+    }
+
+    #[test]
+    fn expansion_overflow() {
+        let image = parse_input(TEST_INPUT);
+
+        assert!(ExpandedImage::new(&image, i64::MAX, 1).is_none());
+        assert!(ExpandedImage::new(&image, 1, i64::MIN).is_none());
+        assert!(ExpandedImage::new(&image, i64::MAX / 4, 1)
+            .unwrap()
+            .sum_of_distances()
+            .is_none());
+        assert_eq!(
+            ExpandedImage::new(&image, i64::MAX / 10_000, 1)
+                .unwrap()
+                .distance(0, 1),
+            Some(i64::MAX / 10_000 + 4)
+        );
+    }
+
+    #[test]
+    fn invalid_factors_and_galaxies() {
+        let image = parse_input(TEST_INPUT);
+
+        for factor in [0, -1, -5, i64::MIN] {
+            assert!(ExpandedImage::new(&image, factor, 1).is_none());
+            assert!(ExpandedImage::new(&image, 1, factor).is_none());
+        }
+
+        let expanded_image = ExpandedImage::new(&image, 2, 2).unwrap();
+
+        assert_eq!(expanded_image.distance(0, 9), None);
+        assert_eq!(expanded_image.nearest(9), None);
+        assert!(ExpandedImage::new(
+            &Image {
+                galaxies: vec![(3, 0)],
+                width: 3,
+                height: 1,
+            },
+            2,
+            2
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn leading_empty_lines_expand() {
+        let expanded_image = ExpandedImage::new(&parse_input("...\n...\n..#"), 2, 2).unwrap();
+
+        assert_eq!(expanded_image.galaxies, vec![(4, 4)]);
+
+        let expanded_image = ExpandedImage::new(&parse_input("....\n..#.\n...#"), 3, 5).unwrap();
+
+        assert_eq!(expanded_image.galaxies, vec![(6, 5), (7, 6)]);
+    }
+
+    #[test]
+    fn nearest_and_farthest_example() {
+        let expanded_image = ExpandedImage::new(&parse_input(TEST_INPUT), 2, 2).unwrap();
+
+        assert_eq!(expanded_image.nearest(0), Some((1, 6)));
+        assert_eq!(expanded_image.farthest(0), Some((5, 15)));
+        assert_eq!(expanded_image.nearest(8), Some((6, 5)));
+        assert_eq!(
+            ExpandedImage::new(&parse_input("...\n.#.\n..."), 2, 2)
+                .unwrap()
+                .nearest(0),
+            None
+        );
+    }
+
+    #[test]
+    fn distance_matrix_csv_example() {
+        let image = Image {
+            galaxies: vec![(0, 0), (2, 1), (4, 0)],
+            width: 5,
+            height: 2,
+        };
+        let expanded_image = ExpandedImage::new(&image, 3, 3).unwrap();
+
+        assert_eq!(
+            expanded_image.distance_matrix_csv(),
+            Some(String::from(",0,1,2\n0,0,5,8\n1,5,0,5\n2,8,5,0"))
+        );
+    }
+
+    #[test]
+    fn render_example() {
+        let image = parse_input(TEST_INPUT);
+
+        assert_eq!(
+            ExpandedImage::new(&image, 2, 2).unwrap().render(),
+            Some(String::from(
+                r"....#........
+.........#...
+#............
+.............
+.............
+........#....
+.#...........
+............#
+.............
+.............
+.........#...
+#....#......."
+            ))
+        );
+        assert!(ExpandedImage::new(&image, 1_000_000, 1_000_000)
+            .unwrap()
+            .render()
+            .is_none());
+    }
+
+    #[test]
+    fn render_keeps_empty_border_lines() {
+        let image = parse_input("...\n...\n..#");
+
+        assert_eq!(
+            ExpandedImage::new(&image, 2, 2).unwrap().render(),
+            Some(String::from(".....\n.....\n.....\n.....\n....#"))
+        );
+
+        let image = parse_input(".....\n.#...\n.....");
+
+        assert_eq!(
+            ExpandedImage::new(&image, 2, 3).unwrap().render(),
+            Some(String::from(
+                ".........\n.........\n.........\n..#......\n.........\n.........\n........."
+            ))
+        );
+    }
 }
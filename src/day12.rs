@@ -1,10 +1,8 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashMap;
-use std::slice;
 use Condition::*;
 
 #[derive(Eq, PartialEq, Clone, Debug, Hash)]
-enum Condition {
+pub enum Condition {
     Operational,
     Damaged,
     Unknown,
@@ -29,108 +27,362 @@ fn parse_input(condition_records: &str) -> Vec<(Vec<Condition>, Vec<usize>)> {
     parser.parse(condition_records).unwrap()
 }
 
-fn arrangements_count_cache(
-    conditions: &[Condition],
-    damaged_groups: &[usize],
-    cache: &mut HashMap<(usize, usize), usize>,
-) -> usize {
-    if let Some(count) = cache.get(&(conditions.len(), damaged_groups.len())) {
-        return *count;
+pub struct ArrangementsTable {
+    counts: Vec<u128>,
+    width: usize,
+}
+
+impl ArrangementsTable {
+    pub fn count(&self, position: usize, group_index: usize) -> u128 {
+        self.counts[position * self.width + group_index]
     }
+}
 
-    let mut count = 0;
+pub fn arrangements_table(conditions: &[Condition], damaged_groups: &[usize]) -> ArrangementsTable {
+    let mut operational_prefix_counts = vec![0; conditions.len() + 1];
 
-    if damaged_groups.is_empty() {
-        count = if conditions.contains(&Damaged) { 0 } else { 1 };
+    for (index, condition) in conditions.iter().enumerate() {
+        operational_prefix_counts[index + 1] =
+            operational_prefix_counts[index] + usize::from(*condition == Operational);
+    }
 
-        cache.insert((conditions.len(), damaged_groups.len()), count);
+    let mut required_lens = vec![0; damaged_groups.len() + 1];
 
-        return count;
+    for (group_index, group_len) in damaged_groups.iter().enumerate().rev() {
+        required_lens[group_index] = required_lens[group_index + 1] + group_len + 1;
     }
 
-    for offset in 0..conditions.len() {
-        if conditions[0..offset].contains(&Damaged) || offset + damaged_groups[0] > conditions.len()
-        {
-            break;
+    let width = damaged_groups.len() + 1;
+    let mut counts = vec![0; (conditions.len() + 1) * width];
+    counts[conditions.len() * width + damaged_groups.len()] = 1;
+
+    for position in (0..conditions.len()).rev() {
+        for group_index in (0..=damaged_groups.len()).rev() {
+            if required_lens[group_index] > conditions.len() - position + 1 {
+                break;
+            }
+
+            let mut count = 0;
+
+            if conditions[position] != Damaged {
+                count += counts[(position + 1) * width + group_index];
+            }
+
+            if let Some(&group_len) = damaged_groups.get(group_index) {
+                let group_end = position + group_len;
+
+                if conditions[position] != Operational
+                    && group_end <= conditions.len()
+                    && operational_prefix_counts[group_end] == operational_prefix_counts[position]
+                {
+                    if group_end == conditions.len() {
+                        count += counts[group_end * width + group_index + 1];
+                    } else if conditions[group_end] != Damaged {
+                        count += counts[(group_end + 1) * width + group_index + 1];
+                    }
+                }
+            }
+
+            counts[position * width + group_index] = count;
         }
+    }
 
-        if conditions[offset..offset + damaged_groups[0]].contains(&Operational) {
-            continue;
+    ArrangementsTable { counts, width }
+}
+
+fn arrangements_count(conditions: &[Condition], damaged_groups: &[usize]) -> u128 {
+    arrangements_table(conditions, damaged_groups).count(0, 0)
+}
+
+pub fn unfold(
+    conditions: &[Condition],
+    damaged_groups: &[usize],
+    factor: usize,
+    separator: Condition,
+) -> (Vec<Condition>, Vec<usize>) {
+    let mut unfolded_conditions = Vec::new();
+
+    for copy in 0..factor {
+        if copy > 0 {
+            unfolded_conditions.push(separator.clone());
         }
 
-        if damaged_groups.len() == 1 {
-            if offset + damaged_groups[0] == conditions.len() {
-                count += 1;
-                break;
-            } else {
-                count +=
-                    arrangements_count_cache(&conditions[offset + damaged_groups[0]..], &[], cache);
-                continue;
-            };
-        } else if offset + damaged_groups[0] + 1 > conditions.len() {
-            break;
-        } else if conditions[offset + damaged_groups[0]] == Damaged {
+        unfolded_conditions.extend_from_slice(conditions);
+    }
+
+    (unfolded_conditions, damaged_groups.repeat(factor))
+}
+
+pub struct Arrangements<'a> {
+    conditions: &'a [Condition],
+    damaged_groups: &'a [usize],
+    table: ArrangementsTable,
+    stack: Vec<(usize, usize, Vec<Condition>)>,
+}
+
+impl Arrangements<'_> {
+    fn is_feasible(&self, position: usize, group_index: usize) -> bool {
+        position <= self.conditions.len() && self.table.count(position, group_index) > 0
+    }
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<Condition>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((position, group_index, mut arrangement)) = self.stack.pop() {
+            if group_index == self.damaged_groups.len() {
+                arrangement.resize(self.conditions.len(), Operational);
+                return Some(arrangement);
+            }
+
+            let group_len = self.damaged_groups[group_index];
+            let is_last_group = group_index + 1 == self.damaged_groups.len();
+            let mut children = Vec::new();
+
+            for start in position..self.conditions.len() {
+                if self.conditions[position..start].contains(&Damaged)
+                    || start + group_len > self.conditions.len()
+                {
+                    break;
+                }
+
+                if self.conditions[start..start + group_len].contains(&Operational)
+                    || self.conditions.get(start + group_len) == Some(&Damaged)
+                {
+                    continue;
+                }
+
+                let next_position = if is_last_group {
+                    start + group_len
+                } else {
+                    start + group_len + 1
+                };
+
+                if !self.is_feasible(next_position, group_index + 1) {
+                    continue;
+                }
+
+                let mut child = arrangement.clone();
+                child.resize(start, Operational);
+                child.resize(start + group_len, Damaged);
+                child.resize(next_position, Operational);
+
+                children.push((next_position, group_index + 1, child));
+            }
+
+            self.stack.extend(children.into_iter().rev());
+        }
+
+        None
+    }
+}
+
+pub fn arrangements<'a>(
+    conditions: &'a [Condition],
+    damaged_groups: &'a [usize],
+) -> Arrangements<'a> {
+    let mut arrangements = Arrangements {
+        conditions,
+        damaged_groups,
+        table: arrangements_table(conditions, damaged_groups),
+        stack: Vec::new(),
+    };
+
+    if arrangements.is_feasible(0, 0) {
+        arrangements.stack.push((0, 0, Vec::new()));
+    }
+
+    arrangements
+}
+
+fn propagate_line(line: &mut [Condition], damaged_groups: &[usize]) -> Option<bool> {
+    if arrangements_count(line, damaged_groups) == 0 {
+        return None;
+    }
+
+    let mut changed = false;
+
+    for index in 0..line.len() {
+        if line[index] != Unknown {
             continue;
         }
 
-        count += arrangements_count_cache(
-            &conditions[offset + damaged_groups[0] + 1..],
-            &damaged_groups[1..],
-            cache,
-        );
+        line[index] = Damaged;
+        let can_be_damaged = arrangements_count(line, damaged_groups) > 0;
+
+        line[index] = Operational;
+        let can_be_operational = arrangements_count(line, damaged_groups) > 0;
+
+        line[index] = match (can_be_damaged, can_be_operational) {
+            (true, true) => Unknown,
+            (true, false) => Damaged,
+            (false, true) => Operational,
+            (false, false) => return None,
+        };
+
+        changed |= line[index] != Unknown;
     }
 
-    cache.insert((conditions.len(), damaged_groups.len()), count);
+    Some(changed)
+}
 
-    count
+pub struct Nonogram {
+    pub row_clues: Vec<Vec<usize>>,
+    pub column_clues: Vec<Vec<usize>>,
 }
 
-fn arrangements_count(conditions: &[Condition], damaged_groups: &[usize]) -> usize {
-    arrangements_count_cache(conditions, damaged_groups, &mut HashMap::new())
+impl Nonogram {
+    pub fn solve(&self) -> Option<Vec<Vec<Condition>>> {
+        self.solve_from(vec![
+            vec![Unknown; self.column_clues.len()];
+            self.row_clues.len()
+        ])
+    }
+
+    pub fn solve_from(&self, mut grid: Vec<Vec<Condition>>) -> Option<Vec<Vec<Condition>>> {
+        loop {
+            let mut changed = false;
+
+            for (row, damaged_groups) in grid.iter_mut().zip(self.row_clues.iter()) {
+                changed |= propagate_line(row, damaged_groups)?;
+            }
+
+            for (column, damaged_groups) in self.column_clues.iter().enumerate() {
+                let mut line = grid
+                    .iter()
+                    .map(|row| row[column].clone())
+                    .collect::<Vec<_>>();
+
+                if propagate_line(&mut line, damaged_groups)? {
+                    changed = true;
+
+                    for (row, condition) in grid.iter_mut().zip(line) {
+                        row[column] = condition;
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let Some((row, column)) = grid.iter().enumerate().find_map(|(row, conditions)| {
+            conditions
+                .iter()
+                .position(|condition| *condition == Unknown)
+                .map(|column| (row, column))
+        }) else {
+            return Some(grid);
+        };
+
+        [Damaged, Operational].into_iter().find_map(|condition| {
+            let mut guess = grid.clone();
+            guess[row][column] = condition;
+
+            self.solve_from(guess)
+        })
+    }
 }
 
 #[aoc(day12, part1)]
-fn part1(condition_records: &[(Vec<Condition>, Vec<usize>)]) -> usize {
+fn part1(condition_records: &[(Vec<Condition>, Vec<usize>)]) -> u128 {
     condition_records
         .iter()
         .map(|(conditions, damaged_groups)| arrangements_count(conditions, damaged_groups))
         .sum()
 }
 
-#[aoc(day12, part2)]
-fn part2(condition_records: &[(Vec<Condition>, Vec<usize>)]) -> usize {
+const UNFOLD_FACTOR: usize = 5;
+
+pub fn unfolded_arrangements_count(
+    condition_records: &[(Vec<Condition>, Vec<usize>)],
+    factor: usize,
+    separator: Condition,
+) -> u128 {
     condition_records
         .iter()
         .map(|(conditions, damaged_groups)| {
-            arrangements_count(
-                &[
-                    &conditions[..],
-                    slice::from_ref(&Unknown),
-                    &conditions[..],
-                    slice::from_ref(&Unknown),
-                    &conditions[..],
-                    slice::from_ref(&Unknown),
-                    &conditions[..],
-                    slice::from_ref(&Unknown),
-                    &conditions[..],
-                ]
-                .concat(),
-                &[
-                    &damaged_groups[..],
-                    &damaged_groups[..],
-                    &damaged_groups[..],
-                    &damaged_groups[..],
-                    &damaged_groups[..],
-                ]
-                .concat(),
-            )
+            let (conditions, damaged_groups) =
+                unfold(conditions, damaged_groups, factor, separator.clone());
+
+            arrangements_count(&conditions, &damaged_groups)
         })
         .sum()
 }
 
+#[aoc(day12, part2)]
+fn part2(condition_records: &[(Vec<Condition>, Vec<usize>)]) -> u128 {
+    unfolded_arrangements_count(condition_records, UNFOLD_FACTOR, Unknown)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    // The HashMap-memoised counter that the dense table replaced, kept only as a
+    // reference oracle for `dense_table_matches_cache` and the benchmark.
+    fn arrangements_count_cache(
+        conditions: &[Condition],
+        damaged_groups: &[usize],
+        cache: &mut HashMap<(usize, usize), usize>,
+    ) -> usize {
+        if let Some(count) = cache.get(&(conditions.len(), damaged_groups.len())) {
+            return *count;
+        }
+
+        let mut count = 0;
+
+        if damaged_groups.is_empty() {
+            count = if conditions.contains(&Damaged) { 0 } else { 1 };
+
+            cache.insert((conditions.len(), damaged_groups.len()), count);
+
+            return count;
+        }
+
+        for offset in 0..conditions.len() {
+            if conditions[0..offset].contains(&Damaged)
+                || offset + damaged_groups[0] > conditions.len()
+            {
+                break;
+            }
+
+            if conditions[offset..offset + damaged_groups[0]].contains(&Operational) {
+                continue;
+            }
+
+            if damaged_groups.len() == 1 {
+                if offset + damaged_groups[0] == conditions.len() {
+                    count += 1;
+                    break;
+                } else {
+                    count += arrangements_count_cache(
+                        &conditions[offset + damaged_groups[0]..],
+                        &[],
+                        cache,
+                    );
+                    continue;
+                };
+            } else if offset + damaged_groups[0] + 1 > conditions.len() {
+                break;
+            } else if conditions[offset + damaged_groups[0]] == Damaged {
+                continue;
+            }
+
+            count += arrangements_count_cache(
+                &conditions[offset + damaged_groups[0] + 1..],
+                &damaged_groups[1..],
+                cache,
+            );
+        }
+
+        cache.insert((conditions.len(), damaged_groups.len()), count);
+
+        count
+    }
 
     static TEST_INPUT: &str = r"???.### 1,1,3
 .??..??...?##. 1,1,3
@@ -148,4 +400,200 @@ mod tests {
     fn part1_example2() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 525_152);
     }
+
+    fn conditions(row: &str) -> Vec<Condition> {
+        row.chars()
+            .map(|condition| match condition {
+                '.' => Operational,
+                '#' => Damaged,
+                _ => Unknown,
+            })
+            .collect()
+    }
+
+    fn groups_of(line: &[Condition]) -> Vec<usize> {
+        line.split(|condition| *condition == Operational)
+            .map(|group| group.len())
+            .filter(|len| *len > 0)
+            .collect()
+    }
+
+    #[test]
+    fn arrangements_example() {
+        let condition_records = parse_input(TEST_INPUT);
+
+        for (conditions, damaged_groups) in condition_records.iter() {
+            let all_arrangements = arrangements(conditions, damaged_groups).collect::<Vec<_>>();
+
+            assert_eq!(
+                all_arrangements.len() as u128,
+                arrangements_count(conditions, damaged_groups)
+            );
+
+            for arrangement in all_arrangements {
+                assert_eq!(&groups_of(&arrangement), damaged_groups);
+                assert!(conditions
+                    .iter()
+                    .zip(arrangement.iter())
+                    .all(|(condition, arranged)| *condition == Unknown || condition == arranged));
+            }
+        }
+
+        assert_eq!(
+            arrangements(&conditions("???.###"), &[1, 1, 3]).collect::<Vec<_>>(),
+            vec![conditions("#.#.###")]
+        );
+        assert_eq!(
+            arrangements(&conditions("????"), &[1, 1])
+                .take(2)
+                .collect::<Vec<_>>(),
+            vec![conditions("#.#."), conditions("#..#")]
+        );
+        assert_eq!(arrangements(&conditions("#.#"), &[3]).next(), None);
+    }
+
+    #[test]
+    fn nonogram_example() {
+        let picture = [".###.", "#...#", "#####", "#...#", "#...#"].map(conditions);
+
+        let nonogram = Nonogram {
+            row_clues: picture.iter().map(|row| groups_of(row)).collect(),
+            column_clues: (0..5)
+                .map(|column| {
+                    groups_of(
+                        &picture
+                            .iter()
+                            .map(|row| row[column].clone())
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect(),
+        };
+
+        assert_eq!(nonogram.solve(), Some(picture.to_vec()));
+    }
+
+    #[test]
+    fn nonogram_backtracking() {
+        let nonogram = Nonogram {
+            row_clues: vec![vec![1], vec![1], vec![1]],
+            column_clues: vec![vec![1], vec![1], vec![1]],
+        };
+
+        let solution = nonogram.solve().unwrap();
+
+        assert_eq!(
+            solution
+                .iter()
+                .map(|row| groups_of(row))
+                .collect::<Vec<_>>(),
+            nonogram.row_clues
+        );
+        assert_eq!(
+            (0..3)
+                .map(|column| {
+                    groups_of(
+                        &solution
+                            .iter()
+                            .map(|row| row[column].clone())
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            nonogram.column_clues
+        );
+
+        let unsolvable = Nonogram {
+            row_clues: vec![vec![2], vec![]],
+            column_clues: vec![vec![], vec![1]],
+        };
+
+        assert_eq!(unsolvable.solve(), None);
+    }
+
+    #[test]
+    fn unfold_factor_and_separator() {
+        let condition_records = parse_input(TEST_INPUT);
+
+        assert_eq!(
+            unfolded_arrangements_count(&condition_records, 1, Unknown),
+            21
+        );
+        assert_eq!(
+            unfolded_arrangements_count(&condition_records, 2, Unknown),
+            1 + 32 + 1 + 2 + 20 + 150
+        );
+        assert_eq!(
+            unfolded_arrangements_count(&condition_records, 2, Operational),
+            1 + 16 + 1 + 1 + 16 + 100
+        );
+        assert_eq!(
+            unfold(&[Damaged], &[1], 3, Operational),
+            (
+                vec![Damaged, Operational, Damaged, Operational, Damaged],
+                vec![1, 1, 1]
+            )
+        );
+    }
+
+    #[test]
+    fn big_counts() {
+        let (conditions, damaged_groups) = unfold(&vec![Unknown; 4], &[1], 40, Unknown);
+
+        assert!(arrangements_count(&conditions, &damaged_groups) > u64::MAX as u128);
+    }
+
+    #[test]
+    fn dense_table_matches_cache() {
+        for (conditions, damaged_groups) in parse_input(TEST_INPUT) {
+            for factor in 1..=5 {
+                let (conditions, damaged_groups) =
+                    unfold(&conditions, &damaged_groups, factor, Unknown);
+
+                assert_eq!(
+                    arrangements_count(&conditions, &damaged_groups),
+                    arrangements_count_cache(&conditions, &damaged_groups, &mut HashMap::new())
+                        as u128
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn benchmark_dense_table_against_cache() {
+        use std::time::Instant;
+
+        let condition_records = parse_input(TEST_INPUT);
+        let unfolded_records = condition_records
+            .iter()
+            .map(|(conditions, damaged_groups)| {
+                unfold(conditions, damaged_groups, UNFOLD_FACTOR, Unknown)
+            })
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut dense_table_total = 0;
+        for _ in 0..1_000 {
+            for (conditions, damaged_groups) in unfolded_records.iter() {
+                dense_table_total += arrangements_count(conditions, damaged_groups);
+            }
+        }
+        let dense_table_duration = start.elapsed();
+
+        let start = Instant::now();
+        let mut cache_total = 0;
+        for _ in 0..1_000 {
+            for (conditions, damaged_groups) in unfolded_records.iter() {
+                cache_total +=
+                    arrangements_count_cache(conditions, damaged_groups, &mut HashMap::new())
+                        as u128;
+            }
+        }
+        let cache_duration = start.elapsed();
+
+        assert_eq!(dense_table_total, cache_total);
+
+        eprintln!("dense table: {dense_table_duration:?}, hash map cache: {cache_duration:?}");
+    }
 }
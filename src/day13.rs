@@ -1,106 +1,288 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashSet;
 
-type Coordinates = (i32, i32);
+pub type Coordinates = (usize, usize);
+
+const CHUNK_BITS: usize = u64::BITS as usize;
+
+type Line = Vec<u64>;
+
+fn set_bit(line: &mut Line, offset: usize) {
+    line[offset / CHUNK_BITS] |= 1 << (offset % CHUNK_BITS);
+}
+
+pub struct Pattern {
+    rows: Vec<Line>,
+    columns: Vec<Line>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatternError {
+    Ragged(usize),
+}
+
+impl Pattern {
+    pub fn new(rows: &[Vec<char>]) -> Result<Self, PatternError> {
+        let width = rows.first().map_or(0, |row| row.len());
+        let height = rows.len();
+
+        if let Some(y) = rows.iter().position(|row| row.len() != width) {
+            return Err(PatternError::Ragged(y));
+        }
+
+        let mut pattern = Pattern {
+            rows: vec![vec![0; width.div_ceil(CHUNK_BITS)]; height],
+            columns: vec![vec![0; height.div_ceil(CHUNK_BITS)]; width],
+        };
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, element) in row.iter().enumerate() {
+                if *element == '#' {
+                    set_bit(&mut pattern.rows[y], x);
+                    set_bit(&mut pattern.columns[x], y);
+                }
+            }
+        }
+
+        Ok(pattern)
+    }
+}
 
 #[aoc_generator(day13)]
-fn parse_input(map: &str) -> Vec<HashSet<Coordinates>> {
+fn parse_input(map: &str) -> Vec<Pattern> {
     use aoc_parse::{parser, prelude::*};
 
     let parser = parser!(
         patterns:sections(
-            rows:lines(any_char+) =>
-                rows.iter().enumerate().flat_map(|(y, row)|
-                    row
-                        .iter()
-                        .enumerate()
-                        .filter_map(move |(x, element)|
-                            if *element == '#' {
-                                Some((x as i32 + 1, y as i32 + 1))
-                            } else {
-                                None
-                            })
-                ).collect::<HashSet<Coordinates>>()
+            rows:lines(any_char+) => Pattern::new(&rows).unwrap()
     ));
 
     parser.parse(map).unwrap()
 }
 
-fn has_reflection(
-    (x, y): &Coordinates,
-    pattern: &HashSet<Coordinates>,
-    max_x: i32,
-    max_y: i32,
-    axis: &i32,
-    axis_is_column: bool,
-) -> bool {
-    if axis_is_column {
-        x <= axis && (2 * axis - x + 1 > max_x || pattern.contains(&(2 * axis - x + 1, *y)))
-            || x > axis && (2 * axis - x + 1 < 1 || pattern.contains(&(2 * axis - x + 1, *y)))
-    } else {
-        y <= axis && (2 * axis - y + 1 > max_y || pattern.contains(&(*x, 2 * axis - y + 1)))
-            || y > axis && (2 * axis - y + 1 < 1 || pattern.contains(&(*x, 2 * axis - y + 1)))
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Axis {
+    Row,
+    Column,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Symmetry {
+    Mirror(Axis),
+    Diagonal,
+    AntiDiagonal,
+    Rotation,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Region {
+    pub origin: Coordinates,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn doubled_centre(&self) -> Coordinates {
+        let (x, y) = self.origin;
+
+        (2 * x + self.width, 2 * y + self.height)
     }
 }
 
-fn summary(pattern: &HashSet<Coordinates>) -> usize {
-    let max_x = pattern.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap().0;
-    let max_y = pattern.iter().max_by(|a, b| a.1.cmp(&b.1)).unwrap().1;
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Reflection {
+    pub symmetry: Symmetry,
+    pub region: Region,
+    pub smudges: Vec<Coordinates>,
+}
 
-    ((1..max_x)
-        .find(|column| {
-            pattern
-                .iter()
-                .all(|coordinates| has_reflection(coordinates, pattern, max_x, max_y, column, true))
-        })
-        .unwrap_or(0)
-        + 100
-            * (1..max_y)
-                .find(|row| {
-                    pattern.iter().all(|coordinates| {
-                        has_reflection(coordinates, pattern, max_x, max_y, row, false)
-                    })
-                })
-                .unwrap_or(0)) as usize
+impl Reflection {
+    pub fn index(&self) -> Option<usize> {
+        let (x, y) = self.region.doubled_centre();
+
+        match self.symmetry {
+            Symmetry::Mirror(Axis::Row) => Some(y / 2),
+            Symmetry::Mirror(Axis::Column) => Some(x / 2),
+            Symmetry::Diagonal | Symmetry::AntiDiagonal | Symmetry::Rotation => None,
+        }
+    }
 }
 
-#[aoc(day13, part1)]
-fn part1(patterns: &[HashSet<Coordinates>]) -> usize {
-    patterns.iter().map(summary).sum()
+fn line_reflection(
+    lines: &[Line],
+    index: usize,
+    smudges_count: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let mut smudges = Vec::new();
+
+    for (before, after) in (0..index).rev().zip(index..lines.len()) {
+        for (chunk, (chunk_before, chunk_after)) in
+            lines[before].iter().zip(lines[after].iter()).enumerate()
+        {
+            let mut differences = chunk_before ^ chunk_after;
+
+            while differences != 0 {
+                if smudges.len() == smudges_count {
+                    return None;
+                }
+
+                smudges.push((
+                    before,
+                    chunk * CHUNK_BITS + differences.trailing_zeros() as usize,
+                ));
+                differences &= differences - 1;
+            }
+        }
+    }
+
+    (smudges.len() == smudges_count).then_some(smudges)
 }
 
-fn corrected_summary(pattern: &HashSet<Coordinates>) -> usize {
-    let max_x = pattern.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap().0;
-    let max_y = pattern.iter().max_by(|a, b| a.1.cmp(&b.1)).unwrap().1;
+fn image(symmetry: Symmetry, (x, y): Coordinates, size: usize) -> Coordinates {
+    match symmetry {
+        Symmetry::Mirror(Axis::Row) => (x, size - 1 - y),
+        Symmetry::Mirror(Axis::Column) => (size - 1 - x, y),
+        Symmetry::Diagonal => (y, x),
+        Symmetry::AntiDiagonal => (size - 1 - y, size - 1 - x),
+        Symmetry::Rotation => (size - 1 - x, size - 1 - y),
+    }
+}
 
-    ((1..max_x)
-        .find(|column| {
-            pattern
-                .iter()
-                .filter(|coordinates| {
-                    has_reflection(coordinates, pattern, max_x, max_y, column, true)
-                })
-                .count()
-                == pattern.len() - 1
-        })
-        .unwrap_or(0)
-        + 100
-            * (1..max_y)
-                .find(|row| {
-                    pattern
-                        .iter()
-                        .filter(|coordinates| {
-                            has_reflection(coordinates, pattern, max_x, max_y, row, false)
+impl Pattern {
+    fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn is_ash(&self, (x, y): Coordinates) -> bool {
+        self.rows[y][x / CHUNK_BITS] & (1 << (x % CHUNK_BITS)) == 0
+    }
+
+    pub fn reflections(&self, axis: Axis, smudges_count: usize) -> Vec<Reflection> {
+        let lines = match axis {
+            Axis::Row => &self.rows,
+            Axis::Column => &self.columns,
+        };
+
+        (1..lines.len())
+            .filter_map(|index| {
+                let half = index.min(lines.len() - index);
+
+                line_reflection(lines, index, smudges_count).map(|smudges| Reflection {
+                    symmetry: Symmetry::Mirror(axis),
+                    region: match axis {
+                        Axis::Row => Region {
+                            origin: (0, index - half),
+                            width: self.width(),
+                            height: 2 * half,
+                        },
+                        Axis::Column => Region {
+                            origin: (index - half, 0),
+                            width: 2 * half,
+                            height: self.height(),
+                        },
+                    },
+                    smudges: smudges
+                        .into_iter()
+                        .map(|(line, offset)| match axis {
+                            Axis::Row => (offset, line),
+                            Axis::Column => (line, offset),
                         })
-                        .count()
-                        == pattern.len() - 1
+                        .collect(),
                 })
-                .unwrap_or(0)) as usize
+            })
+            .collect()
+    }
+
+    pub fn reflection(&self, smudges_count: usize) -> Option<Reflection> {
+        [Axis::Column, Axis::Row]
+            .into_iter()
+            .find_map(|axis| self.reflections(axis, smudges_count).into_iter().next())
+    }
+
+    fn square_symmetry(
+        &self,
+        symmetry: Symmetry,
+        (origin_x, origin_y): Coordinates,
+        size: usize,
+        smudges_count: usize,
+    ) -> Option<Vec<Coordinates>> {
+        let mut smudges = Vec::new();
+
+        for y in 0..size {
+            for x in 0..size {
+                let (image_x, image_y) = image(symmetry, (x, y), size);
+
+                if (image_y, image_x) <= (y, x) {
+                    continue;
+                }
+
+                let position = (origin_x + x, origin_y + y);
+
+                if self.is_ash(position) != self.is_ash((origin_x + image_x, origin_y + image_y)) {
+                    if smudges.len() == smudges_count {
+                        return None;
+                    }
+
+                    smudges.push(position);
+                }
+            }
+        }
+
+        (smudges.len() == smudges_count).then_some(smudges)
+    }
+
+    pub fn square_symmetries(
+        &self,
+        symmetry: Symmetry,
+        size: usize,
+        smudges_count: usize,
+    ) -> Vec<Reflection> {
+        if size == 0 || size > self.width() || size > self.height() {
+            return Vec::new();
+        }
+
+        (0..=self.height() - size)
+            .flat_map(|y| (0..=self.width() - size).map(move |x| (x, y)))
+            .filter_map(|origin| {
+                self.square_symmetry(symmetry, origin, size, smudges_count)
+                    .map(|smudges| Reflection {
+                        symmetry,
+                        region: Region {
+                            origin,
+                            width: size,
+                            height: size,
+                        },
+                        smudges,
+                    })
+            })
+            .collect()
+    }
+}
+
+fn summary(pattern: &Pattern, smudges_count: usize) -> usize {
+    [(Axis::Column, 1), (Axis::Row, 100)]
+        .into_iter()
+        .map(|(axis, multiplier)| {
+            pattern
+                .reflections(axis, smudges_count)
+                .first()
+                .and_then(Reflection::index)
+                .map_or(0, |index| index * multiplier)
+        })
+        .sum()
+}
+
+#[aoc(day13, part1)]
+fn part1(patterns: &[Pattern]) -> usize {
+    patterns.iter().map(|pattern| summary(pattern, 0)).sum()
 }
 
 #[aoc(day13, part2)]
-fn part2(patterns: &[HashSet<Coordinates>]) -> usize {
-    patterns.iter().map(corrected_summary).sum()
+fn part2(patterns: &[Pattern]) -> usize {
+    patterns.iter().map(|pattern| summary(pattern, 1)).sum()
 }
 
 #[cfg(test)]
@@ -132,4 +314,204 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 400);
     }
+
+    #[test]
+    fn reflection_example() {
+        let patterns = parse_input(TEST_INPUT);
+
+        assert_eq!(
+            patterns[0].reflection(0),
+            Some(Reflection {
+                symmetry: Symmetry::Mirror(Axis::Column),
+                region: Region {
+                    origin: (1, 0),
+                    width: 8,
+                    height: 7,
+                },
+                smudges: vec![],
+            })
+        );
+        assert_eq!(
+            patterns[1].reflection(0),
+            Some(Reflection {
+                symmetry: Symmetry::Mirror(Axis::Row),
+                region: Region {
+                    origin: (0, 1),
+                    width: 9,
+                    height: 6,
+                },
+                smudges: vec![],
+            })
+        );
+        assert_eq!(
+            patterns[0].reflection(1),
+            Some(Reflection {
+                symmetry: Symmetry::Mirror(Axis::Row),
+                region: Region {
+                    origin: (0, 0),
+                    width: 9,
+                    height: 6,
+                },
+                smudges: vec![(0, 0)],
+            })
+        );
+        assert_eq!(
+            patterns[1].reflection(1),
+            Some(Reflection {
+                symmetry: Symmetry::Mirror(Axis::Row),
+                region: Region {
+                    origin: (0, 0),
+                    width: 9,
+                    height: 2,
+                },
+                smudges: vec![(4, 0)],
+            })
+        );
+    }
+
+    #[test]
+    fn reflection_with_several_smudges() {
+        let patterns = parse_input(TEST_INPUT);
+
+        for pattern in patterns.iter() {
+            for smudges_count in 0..4 {
+                for reflection in [Axis::Row, Axis::Column]
+                    .into_iter()
+                    .flat_map(|axis| pattern.reflections(axis, smudges_count))
+                {
+                    assert_eq!(reflection.smudges.len(), smudges_count);
+                }
+            }
+        }
+
+        assert_eq!(
+            patterns[1]
+                .reflections(Axis::Column, 2)
+                .iter()
+                .map(Reflection::index)
+                .collect::<Vec<_>>(),
+            vec![Some(7)]
+        );
+    }
+
+    #[test]
+    fn square_symmetries_example() {
+        let pattern = Pattern::new(&[
+            "##.".chars().collect(),
+            ".#.".chars().collect(),
+            "..#".chars().collect(),
+        ])
+        .unwrap();
+
+        let origins = |symmetry, size, smudges_count| {
+            pattern
+                .square_symmetries(symmetry, size, smudges_count)
+                .into_iter()
+                .map(|reflection| reflection.region.origin)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(origins(Symmetry::Diagonal, 2, 0), vec![(1, 1)]);
+        assert_eq!(
+            origins(Symmetry::Diagonal, 2, 1),
+            vec![(0, 0), (1, 0), (0, 1)]
+        );
+
+        for (axis, smudges_count) in [
+            (Symmetry::Diagonal, 1),
+            (Symmetry::AntiDiagonal, 1),
+            (Symmetry::Rotation, 1),
+            (Symmetry::Mirror(Axis::Column), 2),
+            (Symmetry::Mirror(Axis::Row), 3),
+        ] {
+            let symmetries = pattern.square_symmetries(axis, 3, smudges_count);
+
+            assert_eq!(symmetries.len(), 1);
+            assert_eq!(symmetries[0].region.doubled_centre(), (3, 3));
+            assert_eq!(symmetries[0].smudges.len(), smudges_count);
+        }
+
+        assert_eq!(
+            pattern.square_symmetries(Symmetry::Rotation, 3, 1)[0].smudges,
+            vec![(1, 0)]
+        );
+        assert!(pattern
+            .square_symmetries(Symmetry::Rotation, 4, 0)
+            .is_empty());
+    }
+
+    #[test]
+    fn patterns_wider_than_a_chunk() {
+        let mut rows = (0..3)
+            .map(|y| {
+                (0..142)
+                    .map(|x: usize| {
+                        let distance = x.min(141 - x);
+
+                        if (y == 0 && distance.is_multiple_of(2))
+                            || (y == 2 && distance.is_multiple_of(3))
+                        {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            Pattern::new(&rows)
+                .unwrap()
+                .reflections(Axis::Column, 0)
+                .iter()
+                .map(Reflection::index)
+                .collect::<Vec<_>>(),
+            vec![Some(71)]
+        );
+
+        rows[2][140] = '#';
+
+        assert_eq!(
+            Pattern::new(&rows).unwrap().reflection(1),
+            Some(Reflection {
+                symmetry: Symmetry::Mirror(Axis::Column),
+                region: Region {
+                    origin: (0, 0),
+                    width: 142,
+                    height: 3,
+                },
+                smudges: vec![(1, 2)],
+            })
+        );
+
+        let rows = (0..130)
+            .map(|y| vec![if y < 65 { '#' } else { '.' }, '.'])
+            .collect::<Vec<_>>();
+        let pattern = Pattern::new(&rows).unwrap();
+
+        assert_eq!(
+            pattern
+                .reflections(Axis::Row, 0)
+                .iter()
+                .filter_map(Reflection::index)
+                .collect::<Vec<_>>(),
+            (1..=32).chain(98..=129).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            pattern.square_symmetries(Symmetry::Rotation, 2, 0).len(),
+            64
+        );
+    }
+
+    #[test]
+    fn pattern_new_rejects_ragged_rows() {
+        let rows = ["#.#", "..", "###"]
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect::<Vec<_>>();
+
+        assert_eq!(Pattern::new(&rows).err(), Some(PatternError::Ragged(1)));
+        assert!(Pattern::new(&rows[..1]).is_ok());
+    }
 }
@@ -1,162 +1,113 @@
+use crate::day14::Direction::*;
 use crate::day14::Tile::*;
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 enum Tile {
     Rounded,
     CubeShaped,
     EmptySpace,
 }
 
-#[derive(Eq, PartialEq, Clone)]
-struct Platform(Vec<Vec<Tile>>);
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Platform(Vec<Vec<Tile>>);
 
-impl Platform {
-    fn tilt_north(&self) -> Self {
-        let mut tilted_platform = self.clone();
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
 
-        for column in 0..self.0.first().unwrap().len() {
-            let mut cluster_start = 0;
-            let mut cluster_len = 0;
+impl TryFrom<char> for Direction {
+    type Error = char;
 
-            for row in 0..self.0.len() {
-                match self.0[row][column] {
-                    Rounded => {
-                        cluster_len += 1;
-                        tilted_platform.0[row][column] = EmptySpace;
-                    }
-                    CubeShaped => {
-                        for y in cluster_start..cluster_start + cluster_len {
-                            tilted_platform.0[y][column] = Rounded;
-                        }
+    fn try_from(direction: char) -> Result<Self, Self::Error> {
+        match direction {
+            'N' => Ok(North),
+            'W' => Ok(West),
+            'S' => Ok(South),
+            'E' => Ok(East),
+            _ => Err(direction),
+        }
+    }
+}
 
-                        cluster_start = row + 1;
-                        cluster_len = 0;
-                    }
-                    EmptySpace => {
-                        tilted_platform.0[row][column] = EmptySpace;
-                    }
-                }
-            }
+pub fn parse_tilt_sequence(sequence: &str) -> Result<Vec<Direction>, char> {
+    sequence.chars().map(Direction::try_from).collect()
+}
 
-            for y in cluster_start..cluster_start + cluster_len {
-                tilted_platform.0[y][column] = Rounded;
-            }
-        }
+const SPIN_CYCLE: [Direction; 4] = [North, West, South, East];
 
-        tilted_platform
-    }
+impl Platform {
+    pub fn tilt(&self, direction: Direction) -> Self {
+        let height = self.0.len();
+        let width = self.0.first().unwrap().len();
+
+        let (lines_count, line_len) = match direction {
+            North | South => (width, height),
+            West | East => (height, width),
+        };
+
+        let position = |line: usize, offset: usize| match direction {
+            North => (offset, line),
+            West => (line, offset),
+            South => (height - 1 - offset, line),
+            East => (line, width - 1 - offset),
+        };
 
-    fn tilt_west(&self) -> Self {
         let mut tilted_platform = self.clone();
 
-        for row in 0..self.0.len() {
+        for line in 0..lines_count {
             let mut cluster_start = 0;
-            let mut cluster_len = 0;
 
-            for column in 0..self.0.first().unwrap().len() {
+            for offset in 0..line_len {
+                let (row, column) = position(line, offset);
+
                 match self.0[row][column] {
                     Rounded => {
-                        cluster_len += 1;
-                        tilted_platform.0[row][column] = EmptySpace;
-                    }
-                    CubeShaped => {
-                        for x in cluster_start..cluster_start + cluster_len {
-                            tilted_platform.0[row][x] = Rounded;
-                        }
-
-                        cluster_start = column + 1;
-                        cluster_len = 0;
-                    }
-                    EmptySpace => {
                         tilted_platform.0[row][column] = EmptySpace;
-                    }
-                }
-            }
 
-            for x in cluster_start..cluster_start + cluster_len {
-                tilted_platform.0[row][x] = Rounded;
-            }
-        }
+                        let (row, column) = position(line, cluster_start);
+                        tilted_platform.0[row][column] = Rounded;
 
-        tilted_platform
-    }
-
-    fn tilt_south(&self) -> Self {
-        let mut tilted_platform = self.clone();
-
-        for column in 0..self.0.first().unwrap().len() {
-            let mut cluster_start = self.0.len() - 1;
-            let mut cluster_len = 0;
-
-            for row in (0..self.0.len()).rev() {
-                match self.0[row][column] {
-                    Rounded => {
-                        cluster_len += 1;
-                        tilted_platform.0[row][column] = EmptySpace;
+                        cluster_start += 1;
                     }
                     CubeShaped => {
-                        for y in (cluster_start + 1 - cluster_len..=cluster_start).rev() {
-                            tilted_platform.0[y][column] = Rounded;
-                        }
-
-                        cluster_start = if row >= 1 { row - 1 } else { 0 };
-                        cluster_len = 0;
-                    }
-                    EmptySpace => {
-                        tilted_platform.0[row][column] = EmptySpace;
+                        cluster_start = offset + 1;
                     }
+                    EmptySpace => {}
                 }
             }
-
-            for y in (cluster_start + 1 - cluster_len..=cluster_start).rev() {
-                tilted_platform.0[y][column] = Rounded;
-            }
         }
 
         tilted_platform
     }
 
-    fn tilt_east(&self) -> Self {
-        let mut tilted_platform = self.clone();
-
-        for row in 0..self.0.len() {
-            let mut cluster_start = self.0.first().unwrap().len() - 1;
-            let mut cluster_len = 0;
-
-            for column in (0..self.0.first().unwrap().len()).rev() {
-                match self.0[row][column] {
-                    Rounded => {
-                        cluster_len += 1;
-                        tilted_platform.0[row][column] = EmptySpace;
-                    }
-                    CubeShaped => {
-                        for x in (cluster_start + 1 - cluster_len..=cluster_start).rev() {
-                            tilted_platform.0[row][x] = Rounded;
-                        }
+    pub fn tilt_sequence(&self, sequence: &[Direction]) -> Self {
+        sequence.iter().fold(self.clone(), |platform, direction| {
+            platform.tilt(*direction)
+        })
+    }
 
-                        cluster_start = if column >= 1 { column - 1 } else { 0 };
-                        cluster_len = 0;
-                    }
-                    EmptySpace => {
-                        tilted_platform.0[row][column] = EmptySpace;
-                    }
-                }
-            }
+    fn rounded_rocks(&self) -> Vec<u64> {
+        let mut bitset = vec![0; (self.0.len() * self.0.first().unwrap().len()).div_ceil(64)];
 
-            for x in (cluster_start + 1 - cluster_len..=cluster_start).rev() {
-                tilted_platform.0[row][x] = Rounded;
+        for (position, tile) in self.0.iter().flatten().enumerate() {
+            if *tile == Rounded {
+                bitset[position / 64] |= 1 << (position % 64);
             }
         }
 
-        tilted_platform
+        bitset
     }
 
-    fn cycle(&self) -> Self {
-        self.tilt_north().tilt_west().tilt_south().tilt_east()
-    }
-
-    fn load(&self) -> usize {
+    pub fn load(&self) -> usize {
         self.0
             .iter()
             .enumerate()
@@ -167,44 +118,195 @@ impl Platform {
     }
 }
 
-#[aoc_generator(day14)]
-fn parse_input(platform: &str) -> Platform {
-    use aoc_parse::{parser, prelude::*};
+#[derive(Debug)]
+pub enum PlatformError {
+    Parse(aoc_parse::ParseError),
+    Empty,
+    Ragged,
+}
+
+impl FromStr for Platform {
+    type Err = PlatformError;
+
+    fn from_str(platform: &str) -> Result<Self, Self::Err> {
+        use aoc_parse::{parser, prelude::*};
+
+        let parser = parser!(platform:lines({
+            "#" => CubeShaped,
+            "O" => Rounded,
+            "." => EmptySpace,
+        }+) => Platform(platform));
 
-    let parser = parser!(platform:lines((tile:any_char => match tile {
-        '#' => CubeShaped,
-        'O' => Rounded,
-        '.' => EmptySpace,
-        _ => unreachable!(),
-    })+) => Platform(platform));
+        let platform = parser.parse(platform).map_err(PlatformError::Parse)?;
+        let width = platform.0.first().ok_or(PlatformError::Empty)?.len();
 
-    parser.parse(platform).unwrap()
+        if platform.0.iter().any(|row| row.len() != width) {
+            return Err(PlatformError::Ragged);
+        }
+
+        Ok(platform)
+    }
+}
+
+#[aoc_generator(day14)]
+fn parse_input(platform: &str) -> Platform {
+    platform.parse().unwrap()
 }
 
 #[aoc(day14, part1)]
 fn part1(platform: &Platform) -> usize {
-    platform.tilt_north().load()
+    platform.tilt(North).load()
 }
 
-const CYCLES: usize = 1_000_000_000;
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Cycle {
+    pub prefix_len: usize,
+    pub period: usize,
+}
 
-#[aoc(day14, part2)]
-fn part2(platform: &Platform) -> usize {
-    let mut states = vec![platform.to_owned()];
+#[derive(Eq, PartialEq, Debug)]
+pub struct CycleSearch<T> {
+    pub value: T,
+    pub cycle: Option<Cycle>,
+}
+
+impl<T> CycleSearch<T> {
+    fn map<U, F: FnOnce(T) -> U>(self, f: F) -> CycleSearch<U> {
+        CycleSearch {
+            value: f(self.value),
+            cycle: self.cycle,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum CycleDetection {
+    HashMap,
+    Brent,
+}
+
+pub fn state_after<T, K, F, G>(initial_state: &T, step: F, key: G, steps: usize) -> CycleSearch<T>
+where
+    T: Clone,
+    K: Hash + Eq,
+    F: Fn(&T) -> T,
+    G: Fn(&T) -> K,
+{
+    let mut states = vec![initial_state.to_owned()];
+    let mut first_occurrences = HashMap::from([(key(initial_state), 0)]);
+
+    for step_number in 1..=steps {
+        let new_state = step(states.last().unwrap());
+
+        match first_occurrences.entry(key(&new_state)) {
+            Entry::Occupied(entry) => {
+                let prefix_len = *entry.get();
+                let period = step_number - prefix_len;
+
+                return CycleSearch {
+                    value: states[(steps - prefix_len) % period + prefix_len].to_owned(),
+                    cycle: Some(Cycle { prefix_len, period }),
+                };
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(step_number);
+                states.push(new_state);
+            }
+        }
+    }
+
+    CycleSearch {
+        value: states.pop().unwrap(),
+        cycle: None,
+    }
+}
+
+pub fn state_after_brent<T, F>(initial_state: &T, step: F, steps: usize) -> CycleSearch<T>
+where
+    T: Eq + Clone,
+    F: Fn(&T) -> T,
+{
+    let mut state_after_steps = (steps == 0).then(|| initial_state.to_owned());
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = initial_state.to_owned();
+    let mut tortoise_steps = 0;
+    let mut hare = step(initial_state);
+    let mut hare_steps = 1;
+
+    loop {
+        if hare_steps == steps {
+            state_after_steps = Some(hare.clone());
+        }
+
+        if tortoise == hare {
+            break;
+        }
+
+        if power == period {
+            if tortoise_steps >= steps && power >= steps {
+                return CycleSearch {
+                    value: state_after_steps.unwrap(),
+                    cycle: None,
+                };
+            }
+
+            tortoise = hare.clone();
+            tortoise_steps = hare_steps;
+            power *= 2;
+            period = 0;
+        }
+
+        hare = step(&hare);
+        hare_steps += 1;
+        period += 1;
+    }
 
-    for cycle in 1..=CYCLES {
-        let new_state = states.last().unwrap().cycle();
+    let mut tortoise = initial_state.to_owned();
+    let mut hare = (0..period).fold(initial_state.to_owned(), |state, _| step(&state));
+    let mut prefix_len = 0;
 
-        if let Some(first_occurrence) = states.iter().position(|state| *state == new_state) {
-            let cycle_len = cycle - first_occurrence;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix_len += 1;
+    }
 
-            return states[(CYCLES - first_occurrence) % cycle_len + first_occurrence].load();
-        } else {
-            states.push(new_state);
+    if prefix_len + period > steps {
+        return CycleSearch {
+            value: state_after_steps.unwrap(),
+            cycle: None,
+        };
+    }
+
+    CycleSearch {
+        value: (0..(steps - prefix_len) % period).fold(tortoise, |state, _| step(&state)),
+        cycle: Some(Cycle { prefix_len, period }),
+    }
+}
+
+pub fn load_after(
+    platform: &Platform,
+    sequence: &[Direction],
+    repetitions: usize,
+    cycle_detection: CycleDetection,
+) -> CycleSearch<usize> {
+    let step = |platform: &Platform| platform.tilt_sequence(sequence);
+
+    match cycle_detection {
+        CycleDetection::HashMap => {
+            state_after(platform, step, Platform::rounded_rocks, repetitions)
         }
+        CycleDetection::Brent => state_after_brent(platform, step, repetitions),
     }
+    .map(|platform| platform.load())
+}
+
+const CYCLES: usize = 1_000_000_000;
 
-    states.last().unwrap().load()
+#[aoc(day14, part2)]
+fn part2(platform: &Platform) -> usize {
+    load_after(platform, &SPIN_CYCLE, CYCLES, CycleDetection::HashMap).value
 }
 
 #[cfg(test)]
@@ -231,4 +333,114 @@ O.#..O.#.#
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 64);
     }
+
+    #[test]
+    fn tilt_sequence_example() {
+        let platform = parse_input(TEST_INPUT);
+
+        assert_eq!(
+            platform.tilt_sequence(&parse_tilt_sequence("NWSE").unwrap()),
+            platform.tilt_sequence(&SPIN_CYCLE)
+        );
+        assert_eq!(platform.tilt(South).tilt(North), platform.tilt(North));
+        assert_eq!(parse_tilt_sequence("NX"), Err('X'));
+    }
+
+    #[test]
+    fn load_after_example() {
+        let platform = parse_input(TEST_INPUT);
+
+        for cycle_detection in [CycleDetection::HashMap, CycleDetection::Brent] {
+            assert_eq!(
+                load_after(&platform, &SPIN_CYCLE, 3, cycle_detection),
+                CycleSearch {
+                    value: 69,
+                    cycle: None,
+                }
+            );
+            assert_eq!(
+                load_after(&platform, &SPIN_CYCLE, CYCLES, cycle_detection),
+                CycleSearch {
+                    value: 64,
+                    cycle: Some(Cycle {
+                        prefix_len: 3,
+                        period: 7,
+                    }),
+                }
+            );
+            assert_eq!(
+                load_after(&platform, &[North], 5, cycle_detection),
+                CycleSearch {
+                    value: 136,
+                    cycle: Some(Cycle {
+                        prefix_len: 1,
+                        period: 1,
+                    }),
+                }
+            );
+        }
+
+        let sequence = parse_tilt_sequence("NNESW").unwrap();
+
+        assert_eq!(
+            load_after(&platform, &sequence, 100, CycleDetection::Brent).value,
+            load_after(&platform, &sequence, 100, CycleDetection::HashMap).value
+        );
+        assert_eq!(
+            load_after(&platform, &sequence, 100, CycleDetection::HashMap).value,
+            (0..100)
+                .fold(platform, |platform, _| platform.tilt_sequence(&sequence))
+                .load()
+        );
+    }
+
+    #[test]
+    fn cycle_detection_modes_agree() {
+        let step = |state: &usize| if *state < 9 { state + 1 } else { 3 };
+
+        for steps in 0..40 {
+            let hash_map = state_after(&0, step, |state| *state, steps);
+            let brent = state_after_brent(&0, step, steps);
+
+            assert_eq!(hash_map, brent);
+            assert_eq!(
+                hash_map.cycle,
+                (steps >= 10).then_some(Cycle {
+                    prefix_len: 3,
+                    period: 7,
+                })
+            );
+        }
+
+        for steps in 0..5 {
+            let fixed_point = state_after_brent(&0, |state: &usize| *state, steps);
+
+            assert_eq!(
+                fixed_point,
+                state_after(&0, |state| *state, |state| *state, steps)
+            );
+        }
+
+        assert_eq!(
+            state_after_brent(&0, |state: &usize| state + 1, 20),
+            CycleSearch {
+                value: 20,
+                cycle: None,
+            }
+        );
+    }
+
+    #[test]
+    fn platform_from_str() {
+        assert_eq!("O.\n#O".parse::<Platform>().unwrap().load(), 3);
+        assert!(matches!(
+            "O.\n#".parse::<Platform>(),
+            Err(PlatformError::Ragged)
+        ));
+        assert!(matches!(
+            "O.\n#X".parse::<Platform>(),
+            Err(PlatformError::Parse(_))
+        ));
+        assert!(matches!("".parse::<Platform>(), Err(PlatformError::Empty)));
+    }
 }
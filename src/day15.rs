@@ -1,5 +1,6 @@
 use crate::day15::Operation::*;
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::fmt::{self, Display, Formatter};
 
 #[aoc_generator(day15)]
 fn parse_input(initialization_sequence: &str) -> Vec<String> {
@@ -9,15 +10,19 @@ fn parse_input(initialization_sequence: &str) -> Vec<String> {
         .collect()
 }
 
-fn run_hash_algorithm(input: &str) -> usize {
+fn hash(input: &str, modulus: usize) -> usize {
     input
         .as_bytes()
         .iter()
         .fold(0, |current_value, ascii_code| {
-            (current_value + *ascii_code as usize) * 17 % 256
+            (current_value + *ascii_code as usize) * 17 % modulus
         })
 }
 
+fn run_hash_algorithm(input: &str) -> usize {
+    hash(input, 256)
+}
+
 #[aoc(day15, part1)]
 fn part1(initialization_sequence: &[String]) -> usize {
     initialization_sequence
@@ -46,62 +51,337 @@ fn parse_initialization_step(step: &str) -> (String, Operation) {
     parser.parse(step).unwrap()
 }
 
-fn run_hashmap_algorithm(steps: &[(String, Operation)]) -> [Vec<(String, usize)>; 256] {
-    let mut boxes: [Vec<(String, usize)>; 256] =
-        std::iter::repeat_with(|| Vec::with_capacity(steps.len()))
-            .take(256)
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
+pub struct HolidayHashMap<V> {
+    boxes: Vec<Vec<(String, V)>>,
+}
 
-    for (label, operation) in steps {
-        let box_number = run_hash_algorithm(label);
+pub enum Entry<'a, V> {
+    Occupied(OccupiedEntry<'a, V>),
+    Vacant(VacantEntry<'a, V>),
+}
 
-        match operation {
-            Dash => {
-                if let Some(position) = boxes[box_number].iter().position(|(l, _)| *l == *label) {
-                    boxes[box_number].remove(position);
-                }
-            }
-            EqualSign(focal_length) => {
-                if let Some(position) = boxes[box_number].iter().position(|(l, _)| *l == *label) {
-                    let _ = std::mem::replace(
-                        &mut boxes[box_number][position],
-                        (label.clone(), *focal_length),
-                    );
-                } else {
-                    boxes[box_number].push((label.clone(), *focal_length));
-                }
+pub struct OccupiedEntry<'a, V> {
+    lenses: &'a mut Vec<(String, V)>,
+    slot_number: usize,
+}
+
+pub struct VacantEntry<'a, V> {
+    lenses: &'a mut Vec<(String, V)>,
+    label: String,
+}
+
+impl<'a, V> Entry<'a, V> {
+    pub fn label(&self) -> &str {
+        match self {
+            Entry::Occupied(entry) => entry.label(),
+            Entry::Vacant(entry) => &entry.label,
+        }
+    }
+
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify(mut self, modify: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            modify(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+impl<'a, V> OccupiedEntry<'a, V> {
+    pub fn label(&self) -> &str {
+        &self.lenses[self.slot_number].0
+    }
+
+    pub fn get(&self) -> &V {
+        &self.lenses[self.slot_number].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.lenses[self.slot_number].1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.lenses[self.slot_number].1
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.lenses.remove(self.slot_number).1
+    }
+}
+
+impl<'a, V> VacantEntry<'a, V> {
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.lenses.push((self.label, value));
+
+        &mut self.lenses.last_mut().unwrap().1
+    }
+}
+
+impl<V> Default for HolidayHashMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> HolidayHashMap<V> {
+    pub fn new() -> Self {
+        Self::with_box_count(256)
+    }
+
+    pub fn with_box_count(box_count: usize) -> Self {
+        assert!(box_count > 0, "a HolidayHashMap needs at least one box");
+
+        HolidayHashMap {
+            boxes: std::iter::repeat_with(Vec::new).take(box_count).collect(),
+        }
+    }
+
+    pub fn box_count(&self) -> usize {
+        self.boxes.len()
+    }
+
+    pub fn box_number(&self, label: &str) -> usize {
+        hash(label, self.boxes.len())
+    }
+
+    fn slot_number(&self, label: &str) -> Option<usize> {
+        self.boxes[self.box_number(label)]
+            .iter()
+            .position(|(l, _)| l == label)
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.iter().map(|lenses| lenses.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.iter().all(|lenses| lenses.is_empty())
+    }
+
+    pub fn get(&self, label: &str) -> Option<&V> {
+        self.slot_number(label)
+            .map(|slot_number| &self.boxes[self.box_number(label)][slot_number].1)
+    }
+
+    pub fn get_mut(&mut self, label: &str) -> Option<&mut V> {
+        let box_number = self.box_number(label);
+
+        self.slot_number(label)
+            .map(|slot_number| &mut self.boxes[box_number][slot_number].1)
+    }
+
+    pub fn contains_label(&self, label: &str) -> bool {
+        self.slot_number(label).is_some()
+    }
+
+    pub fn entry(&mut self, label: &str) -> Entry<'_, V> {
+        let box_number = self.box_number(label);
+        let slot_number = self.slot_number(label);
+        let lenses = &mut self.boxes[box_number];
+
+        match slot_number {
+            Some(slot_number) => Entry::Occupied(OccupiedEntry {
+                lenses,
+                slot_number,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                lenses,
+                label: label.to_owned(),
+            }),
+        }
+    }
+
+    pub fn insert(&mut self, label: &str, value: V) -> Option<V> {
+        match self.entry(label) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
             }
         }
     }
 
-    boxes
+    pub fn remove(&mut self, label: &str) -> Option<V> {
+        match self.entry(label) {
+            Entry::Occupied(entry) => Some(entry.remove()),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn lenses(&self, box_number: usize) -> impl Iterator<Item = (&str, &V)> {
+        self.boxes[box_number]
+            .iter()
+            .map(|(label, value)| (label.as_str(), value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        (0..self.boxes.len()).flat_map(|box_number| self.lenses(box_number))
+    }
 }
 
-fn focusing_power(boxes: &[Vec<(String, usize)>; 256]) -> usize {
+impl HolidayHashMap<usize> {
+    pub fn focusing_power(&self) -> usize {
+        self.boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(box_number, lenses)| {
+                lenses
+                    .iter()
+                    .enumerate()
+                    .map(move |(slot_number, (_, focal_length))| {
+                        (box_number + 1) * (slot_number + 1) * focal_length
+                    })
+            })
+            .sum()
+    }
+}
+
+impl<V: Display> HolidayHashMap<V> {
+    pub fn box_state(&self, box_number: usize) -> String {
+        format_box(
+            box_number,
+            self.lenses(box_number)
+                .map(|(label, value)| format!("[{label} {value}]")),
+        )
+    }
+
+    pub fn state(&self) -> String {
+        (0..self.boxes.len())
+            .filter(|box_number| !self.boxes[*box_number].is_empty())
+            .map(|box_number| self.box_state(box_number))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn format_box(box_number: usize, lenses: impl Iterator<Item = String>) -> String {
+    std::iter::once(format!("Box {box_number}:"))
+        .chain(lenses)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn apply_operation(boxes: &mut HolidayHashMap<usize>, label: &str, operation: &Operation) {
+    match operation {
+        Dash => {
+            boxes.remove(label);
+        }
+        EqualSign(focal_length) => {
+            boxes.insert(label, *focal_length);
+        }
+    }
+}
+
+fn run_hashmap_algorithm(steps: &[(String, Operation)]) -> HolidayHashMap<usize> {
+    let mut boxes = HolidayHashMap::new();
+
+    for (label, operation) in steps {
+        apply_operation(&mut boxes, label, operation);
+    }
+
     boxes
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TraceMode {
+    Full,
+    Diff,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TraceStep {
+    pub step: String,
+    pub boxes: Vec<(usize, Vec<(String, usize)>)>,
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "After \"{}\":", self.step)?;
+
+        for (box_number, lenses) in self.boxes.iter() {
+            write!(
+                f,
+                "\n{}",
+                format_box(
+                    *box_number,
+                    lenses
+                        .iter()
+                        .map(|(label, focal_length)| format!("[{label} {focal_length}]"))
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn trace(initialization_sequence: &[String], mode: TraceMode) -> Vec<TraceStep> {
+    let mut boxes = HolidayHashMap::new();
+
+    initialization_sequence
         .iter()
-        .enumerate()
-        .flat_map(|(box_number, lenses)| {
-            lenses
-                .iter()
-                .enumerate()
-                .map(move |(slot_number, (_, focal_length))| {
-                    (box_number + 1) * (slot_number + 1) * focal_length
-                })
+        .map(|step| {
+            let (label, operation) = parse_initialization_step(step);
+            apply_operation(&mut boxes, &label, &operation);
+
+            let box_numbers = match mode {
+                TraceMode::Full => (0..boxes.box_count())
+                    .filter(|box_number| boxes.lenses(*box_number).next().is_some())
+                    .collect(),
+                TraceMode::Diff => vec![boxes.box_number(&label)],
+            };
+
+            TraceStep {
+                step: step.clone(),
+                boxes: box_numbers
+                    .into_iter()
+                    .map(|box_number| {
+                        (
+                            box_number,
+                            boxes
+                                .lenses(box_number)
+                                .map(|(label, focal_length)| (label.to_owned(), *focal_length))
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            }
         })
-        .sum()
+        .collect()
+}
+
+pub fn trace_report(initialization_sequence: &[String], mode: TraceMode) -> String {
+    trace(initialization_sequence, mode)
+        .iter()
+        .map(|step| step.to_string())
+        .collect::<Vec<_>>()
+        .join("\n\n")
 }
 
 #[aoc(day15, part2)]
 fn part2(initialization_sequence: &[String]) -> usize {
-    focusing_power(&run_hashmap_algorithm(
+    run_hashmap_algorithm(
         &initialization_sequence
             .iter()
             .map(|step| parse_initialization_step(step))
             .collect::<Vec<_>>(),
-    ))
+    )
+    .focusing_power()
 }
 
 #[cfg(test)]
@@ -126,4 +406,171 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT_2)), 145);
     }
+
+    fn replay_against_std(box_count: usize) {
+        use std::collections::HashMap;
+
+        let mut holiday_hash_map = HolidayHashMap::with_box_count(box_count);
+        let mut hash_map = HashMap::new();
+
+        for step in parse_input(TEST_INPUT_2) {
+            let (label, operation) = parse_initialization_step(&step);
+
+            match operation {
+                Dash => assert_eq!(holiday_hash_map.remove(&label), hash_map.remove(&label)),
+                EqualSign(focal_length) => assert_eq!(
+                    holiday_hash_map.insert(&label, focal_length),
+                    hash_map.insert(label.clone(), focal_length)
+                ),
+            }
+
+            assert_eq!(holiday_hash_map.len(), hash_map.len());
+
+            for label in ["rn", "cm", "qp", "pc", "ot", "ab"] {
+                assert_eq!(holiday_hash_map.get(label), hash_map.get(label));
+            }
+
+            let mut contents = holiday_hash_map
+                .iter()
+                .map(|(label, focal_length)| (label.to_owned(), *focal_length))
+                .collect::<Vec<_>>();
+            contents.sort();
+
+            let mut expected = hash_map.clone().into_iter().collect::<Vec<_>>();
+            expected.sort();
+
+            assert_eq!(contents, expected);
+        }
+    }
+
+    #[test]
+    fn holiday_hash_map_matches_std_hash_map() {
+        for box_count in [1, 2, 7, 256] {
+            replay_against_std(box_count);
+        }
+    }
+
+    #[test]
+    fn holiday_hash_map_example() {
+        let boxes = run_hashmap_algorithm(
+            &parse_input(TEST_INPUT_2)
+                .iter()
+                .map(|step| parse_initialization_step(step))
+                .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(
+            boxes.lenses(0).collect::<Vec<_>>(),
+            vec![("rn", &1), ("cm", &2)]
+        );
+        assert_eq!(boxes.lenses(1).count(), 0);
+        assert_eq!(
+            boxes.lenses(3).collect::<Vec<_>>(),
+            vec![("ot", &7), ("ab", &5), ("pc", &6)]
+        );
+        assert_eq!(boxes.focusing_power(), 145);
+    }
+
+    #[test]
+    fn holiday_hash_map_entry() {
+        let mut boxes = HolidayHashMap::with_box_count(4);
+
+        *boxes.entry("rn").or_insert(0) += 1;
+        *boxes.entry("rn").or_insert(0) += 1;
+        boxes
+            .entry("cm")
+            .and_modify(|count| *count += 10)
+            .or_insert(5);
+        boxes
+            .entry("cm")
+            .and_modify(|count| *count += 10)
+            .or_insert(5);
+
+        assert_eq!(boxes.get("rn"), Some(&2));
+        assert_eq!(boxes.get("cm"), Some(&15));
+        assert_eq!(boxes.entry("qp").label(), "qp");
+
+        if let Entry::Occupied(entry) = boxes.entry("rn") {
+            assert_eq!(entry.remove(), 2);
+        }
+
+        assert!(!boxes.contains_label("rn"));
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes.box_count(), 4);
+    }
+
+    #[test]
+    fn trace_example() {
+        let initialization_sequence = parse_input(TEST_INPUT_2);
+
+        assert_eq!(
+            trace_report(&initialization_sequence, TraceMode::Full),
+            r#"After "rn=1":
+Box 0: [rn 1]
+
+After "cm-":
+Box 0: [rn 1]
+
+After "qp=3":
+Box 0: [rn 1]
+Box 1: [qp 3]
+
+After "cm=2":
+Box 0: [rn 1] [cm 2]
+Box 1: [qp 3]
+
+After "qp-":
+Box 0: [rn 1] [cm 2]
+
+After "pc=4":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4]
+
+After "ot=9":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9]
+
+After "ab=5":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9] [ab 5]
+
+After "pc-":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5]
+
+After "pc=6":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5] [pc 6]
+
+After "ot=7":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 7] [ab 5] [pc 6]"#
+        );
+    }
+
+    #[test]
+    fn trace_diff_example() {
+        let initialization_sequence = parse_input(TEST_INPUT_2);
+        let steps = trace(&initialization_sequence, TraceMode::Diff);
+
+        assert_eq!(steps.len(), initialization_sequence.len());
+        assert_eq!(steps[1].to_string(), "After \"cm-\":\nBox 0: [rn 1]");
+        assert_eq!(steps[4].to_string(), "After \"qp-\":\nBox 1:");
+        assert_eq!(
+            steps[10].to_string(),
+            "After \"ot=7\":\nBox 3: [ot 7] [ab 5] [pc 6]"
+        );
+        assert!(steps.iter().all(|step| step.boxes.len() == 1));
+    }
+
+    #[test]
+    fn state_example() {
+        let mut boxes = HolidayHashMap::new();
+        boxes.insert("rn", 1);
+        boxes.insert("cm", 2);
+        boxes.insert("pc", 4);
+
+        assert_eq!(boxes.state(), "Box 0: [rn 1] [cm 2]\nBox 3: [pc 4]");
+        assert_eq!(boxes.box_state(1), "Box 1:");
+    }
 }
@@ -172,8 +172,8 @@ fn part2(module_configuration: &[(ModuleType, String, Vec<String>)]) -> usize {
         .conjunction_states
         .get(last_conjunction)
         .unwrap()
-        .iter()
-        .map(|(module_name, _)| (module_name.to_owned(), None))
+        .keys()
+        .map(|module_name| (module_name.to_owned(), None))
         .collect::<HashMap<String, Option<usize>>>();
 
     let mut pressed_count = 0;
@@ -1,11 +1,12 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
 
-type Coordinates2 = (i64, i64);
-type Coordinates3 = (i64, i64, i64);
+pub type Coordinates2 = (i64, i64);
+pub type Coordinates3 = (i64, i64, i64);
 
 type Rectangle = (Coordinates2, Coordinates2);
-type Brick = (Coordinates3, Coordinates3);
+pub type Brick = (Coordinates3, Coordinates3);
 
 #[aoc_generator(day22)]
 fn parse_input(snapshot: &str) -> Vec<Brick> {
@@ -19,13 +20,6 @@ fn parse_input(snapshot: &str) -> Vec<Brick> {
     parser.parse(snapshot).unwrap()
 }
 
-fn overlap(rectangle1: Rectangle, rectangle2: Rectangle) -> bool {
-    rectangle1.0 .0 <= rectangle2.1 .0
-        && rectangle1.1 .0 >= rectangle2.0 .0
-        && rectangle1.0 .1 <= rectangle2.1 .1
-        && rectangle1.1 .1 >= rectangle2.0 .1
-}
-
 fn base(brick: &Brick) -> Rectangle {
     ((brick.0 .0, brick.0 .1), (brick.1 .0, brick.1 .1))
 }
@@ -37,112 +31,363 @@ fn vertical_shift(brick: &Brick, z: i64) -> Brick {
     )
 }
 
-fn land(snapshot: &[Brick]) -> Vec<Brick> {
+pub fn land(snapshot: &[Brick]) -> Vec<Brick> {
     let mut landed_bricks = snapshot.to_vec();
-    landed_bricks.sort_unstable_by(|(bottom1, _), (bottom2, _)| bottom1.2.cmp(&bottom2.2));
+    landed_bricks.sort_by_key(|(bottom, _)| bottom.2);
 
-    for index in 0..landed_bricks.len() {
-        let brick = landed_bricks[index];
+    let mut heightmap: HashMap<Coordinates2, i64> = HashMap::new();
 
-        let max_z = landed_bricks[0..index]
-            .iter()
-            .filter_map(|overlapping_brick| {
-                if overlap(base(&brick), base(overlapping_brick)) {
-                    Some(overlapping_brick.1 .2)
-                } else {
-                    None
-                }
-            })
+    for brick in landed_bricks.iter_mut() {
+        let max_z = cells(base(brick))
+            .filter_map(|cell| heightmap.get(&cell))
             .max()
+            .copied()
             .unwrap_or(0);
 
-        landed_bricks[index] = vertical_shift(&brick, max_z + 1);
+        *brick = vertical_shift(brick, max_z + 1);
+
+        for cell in cells(base(brick)) {
+            heightmap.insert(cell, brick.1 .2);
+        }
     }
 
     landed_bricks
 }
 
-fn supporting_bricks(bricks: &[Brick]) -> HashMap<Brick, HashSet<Brick>> {
-    bricks
-        .iter()
-        .map(|brick| {
-            (
-                *brick,
-                bricks
-                    .iter()
-                    .filter_map(|supporting_brick| {
-                        if overlap(base(brick), base(supporting_brick))
-                            && brick.0 .2 == supporting_brick.1 .2 + 1
-                        {
-                            Some(*supporting_brick)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<HashSet<_>>(),
-            )
-        })
-        .collect::<HashMap<_, _>>()
+fn cells(rectangle: Rectangle) -> impl Iterator<Item = Coordinates2> {
+    (rectangle.0 .0..=rectangle.1 .0)
+        .flat_map(move |x| (rectangle.0 .1..=rectangle.1 .1).map(move |y| (x, y)))
 }
 
-#[aoc(day22, part1)]
-fn part1(snapshot: &[Brick]) -> usize {
-    let landed_bricks = land(snapshot);
-
-    let result: HashSet<Brick> = supporting_bricks(&landed_bricks)
-        .values()
-        .filter_map(|bricks| {
-            if bricks.len() == 1 {
-                Some(*bricks.iter().next().unwrap())
-            } else {
-                None
+pub struct SupportGraph {
+    pub supports: Vec<Vec<usize>>,
+    pub supported_by: Vec<Vec<usize>>,
+}
+
+pub fn supporting_bricks(bricks: &[Brick]) -> SupportGraph {
+    let mut order = (0..bricks.len()).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&index| bricks[index].0 .2);
+
+    let mut supports = vec![Vec::new(); bricks.len()];
+    let mut supported_by = vec![Vec::new(); bricks.len()];
+
+    let mut heightmap: HashMap<Coordinates2, (i64, usize)> = HashMap::new();
+
+    for index in order {
+        let brick = &bricks[index];
+
+        for cell in cells(base(brick)) {
+            if let Some(&(z, supporting_index)) = heightmap.get(&cell) {
+                if z + 1 == brick.0 .2 && !supported_by[index].contains(&supporting_index) {
+                    supported_by[index].push(supporting_index);
+                    supports[supporting_index].push(index);
+                }
             }
-        })
-        .collect::<HashSet<_>>();
 
-    landed_bricks.len() - result.len()
+            heightmap.insert(cell, (brick.1 .2, index));
+        }
+    }
+
+    SupportGraph {
+        supports,
+        supported_by,
+    }
 }
 
-#[aoc(day22, part2)]
-fn part2(snapshot: &[(Coordinates3, Coordinates3)]) -> usize {
-    let landed_bricks = land(snapshot);
-    let supporting_bricks = supporting_bricks(&landed_bricks);
+impl SupportGraph {
+    fn topological_order(&self) -> Vec<usize> {
+        let mut remaining_supports = self
+            .supported_by
+            .iter()
+            .map(|supporting| supporting.len())
+            .collect::<Vec<_>>();
+        let mut order = (0..self.supported_by.len())
+            .filter(|&index| remaining_supports[index] == 0)
+            .collect::<Vec<_>>();
+        let mut position = 0;
+
+        while let Some(&index) = order.get(position) {
+            for &supported in self.supports[index].iter() {
+                remaining_supports[supported] -= 1;
+
+                if remaining_supports[supported] == 0 {
+                    order.push(supported);
+                }
+            }
 
-    let mut result = 0;
+            position += 1;
+        }
 
-    let mut cache: HashMap<Brick, HashSet<Brick>> = HashMap::new();
+        order
+    }
 
-    for brick in landed_bricks.into_iter().rev() {
-        let mut removed_bricks = HashSet::from([brick]);
+    pub fn falling_if_removed(&self, index: usize) -> Vec<usize> {
+        self.falling_if_removed_all(&[index])
+    }
 
-        loop {
-            let mut new_removed_bricks = HashSet::new();
+    pub fn falling_if_removed_all(&self, indices: &[usize]) -> Vec<usize> {
+        let mut removed = vec![false; self.supports.len()];
 
-            for (supported_brick, supporting_bricks) in supporting_bricks.iter() {
-                if !removed_bricks.contains(supported_brick)
-                    && !supporting_bricks.is_empty()
-                    && supporting_bricks.difference(&removed_bricks).count() == 0
+        for &index in indices {
+            removed[index] = true;
+        }
+
+        let mut queue = indices.iter().copied().collect::<VecDeque<_>>();
+        let mut fallen = Vec::new();
+
+        while let Some(current) = queue.pop_front() {
+            for &supported in self.supports[current].iter() {
+                if !removed[supported]
+                    && self.supported_by[supported]
+                        .iter()
+                        .all(|&supporting| removed[supporting])
                 {
-                    new_removed_bricks.insert(*supported_brick);
-                    new_removed_bricks.extend(
-                        cache
-                            .get(supported_brick)
-                            .unwrap_or(&HashSet::with_capacity(0)),
-                    );
+                    removed[supported] = true;
+                    fallen.push(supported);
+                    queue.push_back(supported);
                 }
             }
+        }
+
+        fallen
+    }
+
+    pub fn safe_bricks(&self) -> Vec<usize> {
+        (0..self.supports.len())
+            .filter(|&index| {
+                self.supports[index]
+                    .iter()
+                    .all(|&supported| self.supported_by[supported].len() > 1)
+            })
+            .collect()
+    }
+
+    pub fn chain_reactions(&self) -> Vec<usize> {
+        let ground = self.supports.len();
+
+        let mut dominator = vec![ground; ground + 1];
+        let mut depth = vec![0; ground + 1];
+
+        let order = self.topological_order();
+
+        for &index in order.iter() {
+            let mut supporting = self.supported_by[index].iter().copied();
+
+            let Some(first) = supporting.next() else {
+                depth[index] = 1;
+                continue;
+            };
+
+            let common = supporting.fold(first, |mut a, mut b| {
+                while a != b {
+                    if depth[a] >= depth[b] {
+                        a = dominator[a];
+                    } else {
+                        b = dominator[b];
+                    }
+                }
+
+                a
+            });
+
+            dominator[index] = common;
+            depth[index] = depth[common] + 1;
+        }
+
+        let mut subtree_sizes = vec![1; ground + 1];
+
+        for &index in order.iter().rev() {
+            subtree_sizes[dominator[index]] += subtree_sizes[index];
+        }
+
+        subtree_sizes.truncate(ground);
+        subtree_sizes.iter_mut().for_each(|size| *size -= 1);
+        subtree_sizes
+    }
+}
+
+pub fn resettle(landed_bricks: &[Brick], removed: &[usize]) -> Vec<(usize, Brick)> {
+    let (indices, remaining_bricks): (Vec<usize>, Vec<Brick>) = landed_bricks
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !removed.contains(index))
+        .unzip();
+
+    let mut order = (0..indices.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&position| remaining_bricks[position].0 .2);
+
+    order
+        .into_iter()
+        .map(|position| indices[position])
+        .zip(land(&remaining_bricks))
+        .collect()
+}
+
+#[derive(Copy, Clone)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+fn label(index: usize) -> char {
+    char::from(b'A' + (index % 26) as u8)
+}
+
+pub fn side_projection(bricks: &[Brick], axis: Axis) -> String {
+    let horizontal_range = |brick: &Brick| match axis {
+        Axis::X => (brick.0 .0, brick.1 .0),
+        Axis::Y => (brick.0 .1, brick.1 .1),
+    };
+
+    let min_h = bricks
+        .iter()
+        .map(|brick| horizontal_range(brick).0)
+        .min()
+        .unwrap_or(0);
+    let max_h = bricks
+        .iter()
+        .map(|brick| horizontal_range(brick).1)
+        .max()
+        .unwrap_or(0);
+    let max_z = bricks.iter().map(|brick| brick.1 .2).max().unwrap_or(0);
+
+    let width = (max_h - min_h + 1) as usize;
+    let mut rows = vec![vec![None; width]; max_z as usize + 1];
 
-            if !new_removed_bricks.is_empty() {
-                result += new_removed_bricks.len();
-                removed_bricks.extend(new_removed_bricks);
-            } else {
-                cache.insert(brick, removed_bricks);
-                break;
+    for (index, brick) in bricks.iter().enumerate() {
+        let (start, end) = horizontal_range(brick);
+
+        for z in brick.0 .2..=brick.1 .2 {
+            for h in start..=end {
+                let cell = &mut rows[z as usize][(h - min_h) as usize];
+
+                *cell = match cell {
+                    None => Some(label(index)),
+                    Some(_) => Some('?'),
+                };
             }
         }
     }
 
-    result
+    let axis_name = match axis {
+        Axis::X => "x",
+        Axis::Y => "y",
+    };
+
+    let mut projection = String::new();
+
+    writeln!(projection, "{}", format!("{axis_name:^width$}").trim_end()).unwrap();
+    writeln!(
+        projection,
+        "{}",
+        (min_h..=max_h)
+            .map(|h| char::from_digit(h.rem_euclid(10) as u32, 10).unwrap())
+            .collect::<String>()
+    )
+    .unwrap();
+
+    for z in (1..=max_z).rev() {
+        let row = rows[z as usize]
+            .iter()
+            .map(|cell| cell.unwrap_or('.'))
+            .collect::<String>();
+
+        if z == (max_z + 1) / 2 {
+            writeln!(projection, "{row} {z} z").unwrap();
+        } else {
+            writeln!(projection, "{row} {z}").unwrap();
+        }
+    }
+
+    write!(projection, "{} 0", "-".repeat(width)).unwrap();
+
+    projection
+}
+
+fn box_vertices(brick: &Brick) -> [Coordinates3; 8] {
+    let ((x0, y0, z0), (x1, y1, z1)) = *brick;
+    let (x1, y1, z1) = (x1 + 1, y1 + 1, z1 + 1);
+
+    [
+        (x0, y0, z0),
+        (x1, y0, z0),
+        (x1, y1, z0),
+        (x0, y1, z0),
+        (x0, y0, z1),
+        (x1, y0, z1),
+        (x1, y1, z1),
+        (x0, y1, z1),
+    ]
+}
+
+const BOX_FACES: [[usize; 4]; 6] = [
+    [0, 3, 2, 1],
+    [4, 5, 6, 7],
+    [0, 1, 5, 4],
+    [1, 2, 6, 5],
+    [2, 3, 7, 6],
+    [3, 0, 4, 7],
+];
+
+pub fn to_obj(bricks: &[Brick]) -> String {
+    let mut obj = String::new();
+
+    for (index, brick) in bricks.iter().enumerate() {
+        writeln!(obj, "o brick_{index}").unwrap();
+
+        for (x, y, z) in box_vertices(brick) {
+            writeln!(obj, "v {x} {y} {z}").unwrap();
+        }
+
+        for face in BOX_FACES {
+            let [a, b, c, d] = face.map(|vertex| index * 8 + vertex + 1);
+            writeln!(obj, "f {a} {b} {c} {d}").unwrap();
+        }
+    }
+
+    obj
+}
+
+pub fn to_ply(bricks: &[Brick]) -> String {
+    let mut ply = String::new();
+
+    writeln!(ply, "ply").unwrap();
+    writeln!(ply, "format ascii 1.0").unwrap();
+    writeln!(ply, "element vertex {}", bricks.len() * 8).unwrap();
+    writeln!(ply, "property int x").unwrap();
+    writeln!(ply, "property int y").unwrap();
+    writeln!(ply, "property int z").unwrap();
+    writeln!(ply, "element face {}", bricks.len() * 6).unwrap();
+    writeln!(ply, "property list uchar int vertex_indices").unwrap();
+    writeln!(ply, "end_header").unwrap();
+
+    for brick in bricks.iter() {
+        for (x, y, z) in box_vertices(brick) {
+            writeln!(ply, "{x} {y} {z}").unwrap();
+        }
+    }
+
+    for index in 0..bricks.len() {
+        for face in BOX_FACES {
+            let [a, b, c, d] = face.map(|vertex| index * 8 + vertex);
+            writeln!(ply, "4 {a} {b} {c} {d}").unwrap();
+        }
+    }
+
+    ply
+}
+
+#[aoc(day22, part1)]
+fn part1(snapshot: &[Brick]) -> usize {
+    supporting_bricks(&land(snapshot)).safe_bricks().len()
+}
+
+#[aoc(day22, part2)]
+fn part2(snapshot: &[Brick]) -> usize {
+    supporting_bricks(&land(snapshot))
+        .chain_reactions()
+        .iter()
+        .sum()
 }
 
 #[cfg(test)]
@@ -166,4 +411,115 @@ mod tests {
     fn part2_example1() {
         assert_eq!(part2(&parse_input(TEST_INPUT_1)), 7);
     }
+
+    #[test]
+    fn falling_if_removed_example1() {
+        let support_graph = supporting_bricks(&land(&parse_input(TEST_INPUT_1)));
+
+        assert_eq!(support_graph.falling_if_removed(0).len(), 6);
+        assert_eq!(support_graph.falling_if_removed(5).len(), 1);
+        assert!(support_graph.falling_if_removed(1).is_empty());
+    }
+
+    #[test]
+    fn chain_reactions_example1() {
+        let support_graph = supporting_bricks(&land(&parse_input(TEST_INPUT_1)));
+
+        assert_eq!(
+            support_graph.chain_reactions(),
+            (0..support_graph.supports.len())
+                .map(|index| support_graph.falling_if_removed(index).len())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(support_graph.safe_bricks(), vec![1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn falling_if_removed_all_example1() {
+        let support_graph = supporting_bricks(&land(&parse_input(TEST_INPUT_1)));
+
+        let mut fallen = support_graph.falling_if_removed_all(&[1, 2]);
+        fallen.sort_unstable();
+
+        assert_eq!(fallen, vec![3, 4, 5, 6]);
+        assert!(support_graph.falling_if_removed_all(&[3, 6]).is_empty());
+    }
+
+    #[test]
+    fn resettle_example1() {
+        let landed_bricks = land(&parse_input(TEST_INPUT_1));
+        let resettled_bricks = resettle(&landed_bricks, &[1, 2]);
+
+        assert_eq!(
+            resettled_bricks,
+            vec![
+                (0, ((1, 0, 1), (1, 2, 1))),
+                (3, ((0, 0, 1), (0, 2, 1))),
+                (4, ((2, 0, 1), (2, 2, 1))),
+                (5, ((0, 1, 2), (2, 1, 2))),
+                (6, ((1, 1, 3), (1, 1, 4))),
+            ]
+        );
+
+        let resettled_bricks = resettled_bricks
+            .into_iter()
+            .map(|(_, brick)| brick)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            supporting_bricks(&resettled_bricks).safe_bricks(),
+            vec![0, 1, 2, 4]
+        );
+    }
+
+    #[test]
+    fn side_projection_example1() {
+        let landed_bricks = land(&parse_input(TEST_INPUT_1));
+
+        assert_eq!(
+            side_projection(&landed_bricks, Axis::X),
+            r" x
+012
+.G. 6
+.G. 5
+FFF 4
+D.E 3 z
+??? 2
+.A. 1
+--- 0"
+        );
+        assert_eq!(
+            side_projection(&landed_bricks, Axis::Y),
+            r" y
+012
+.G. 6
+.G. 5
+.F. 4
+??? 3 z
+B.C 2
+AAA 1
+--- 0"
+        );
+    }
+
+    #[test]
+    fn mesh_export_example1() {
+        let landed_bricks = land(&parse_input(TEST_INPUT_1));
+
+        let obj = to_obj(&landed_bricks);
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("v ")).count(),
+            56
+        );
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("f ")).count(),
+            42
+        );
+        assert!(obj.starts_with("o brick_0\nv 1 0 1\nv 2 0 1\n"));
+
+        let ply = to_ply(&landed_bricks);
+        assert!(ply.contains("element vertex 56\n"));
+        assert!(ply.contains("element face 42\n"));
+        assert!(ply.ends_with("4 51 48 52 55\n"));
+    }
 }